@@ -0,0 +1,239 @@
+//! Additive synthesis, a bank of sine partials with independent amplitude,
+//! frequency ratio and phase.
+//!
+//! Each partial is a rotating phasor, advanced by a complex multiplication per
+//! sample instead of computing a sine, which makes hundreds of partials cheap.
+//! Partials at or above nyquist are muted, with a short fade below it, so that
+//! sweeping the frequency doesn't alias or click.
+//!
+//! Besides setting the partials one by one, the bank can be loaded from the
+//! spectrum of an FFT frame, for resynthesis.
+//!
+//! # Caveats
+//! Amplitude changes are applied right away and are not smoothed, changing
+//! them abruptly at audio rate can click. Changing the frequency or the ratios
+//! recomputes the rotation of every partial, which costs a sine and a cosine
+//! each, so avoid modulating them at every sample with many partials.
+
+use std::f64::consts;
+
+use num::complex::Complex;
+
+use crate::traits::Source;
+
+/// Maximum number of partials.
+pub const MAX_PARTIALS: usize = 1024;
+
+// partials fade out linearly over this fraction of nyquist, below nyquist
+const NYQUIST_FADE: f64 = 0.1;
+
+#[derive(Clone, Copy)]
+struct Partial {
+    amp: f64,
+    ratio: f64,
+    // phase restored by `reset()`
+    phase: f64,
+    // fade out near nyquist
+    gain: f64,
+    // current phasor, the output is its imaginary part
+    re: f64,
+    im: f64,
+    // rotation of the phasor per sample
+    rot_re: f64,
+    rot_im: f64,
+}
+
+impl Partial {
+    fn new(ratio: f64) -> Self {
+        Self {
+            amp: 0.0,
+            ratio,
+            phase: 0.0,
+            gain: 1.0,
+            re: 1.0,
+            im: 0.0,
+            rot_re: 1.0,
+            rot_im: 0.0,
+        }
+    }
+}
+
+/// Bank of sine partials, with frequencies as ratios of a fundamental.
+///
+/// # Examples
+/// A band-limited square wave, from its odd harmonics:
+/// ```
+/// use dsp_lab::core::additive::AdditiveOsc;
+/// use dsp_lab::traits::Source;
+/// let mut osc = AdditiveOsc::new();
+/// osc.set_freq(110.0);
+/// let amps: Vec<f64> = (1..=200)
+///     .map(|k| if k % 2 == 1 { 4.0 / (std::f64::consts::PI * k as f64) } else { 0.0 })
+///     .collect();
+/// osc.set_harmonics(&amps);
+/// let y = osc.step();
+/// ```
+///
+/// Inharmonic partials, for a bell:
+/// ```
+/// use dsp_lab::core::additive::AdditiveOsc;
+/// use dsp_lab::traits::Source;
+/// let mut osc = AdditiveOsc::new();
+/// osc.set_num_partials(4);
+/// for (i, (ratio, amp)) in [(1.0, 0.5), (2.76, 0.3), (5.4, 0.2), (8.93, 0.1)].iter().enumerate() {
+///     osc.set_ratio(i, *ratio);
+///     osc.set_amp(i, *amp);
+/// }
+/// let y = osc.step();
+/// ```
+pub struct AdditiveOsc {
+    partials: Vec<Partial>,
+    freq: f64,
+    sr: f64,
+}
+
+impl AdditiveOsc {
+    /// Creates a bank with a single partial at a ratio of 1 and unit amplitude,
+    /// i.e. a sine.
+    pub fn new() -> Self {
+        let mut ret = Self {
+            partials: vec![Partial::new(1.0)],
+            freq: 440.0,
+            sr: 44100.0,
+        };
+        ret.partials[0].amp = 1.0;
+        ret.update_rotations();
+        ret
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        self.sr = sr;
+        self.update_rotations();
+    }
+
+    /// Sets the fundamental frequency in hertz, which the ratios of the
+    /// partials multiply. This is a method and not a field, because the
+    /// rotation of each partial is precomputed.
+    pub fn set_freq(&mut self, freq: f64) {
+        self.freq = freq;
+        self.update_rotations();
+    }
+
+    /// Sets the number of partials, clamped between 1 and `MAX_PARTIALS`. New
+    /// partials are harmonic, i.e. partial `i` has a ratio of `i + 1`, and are
+    /// silent.
+    pub fn set_num_partials(&mut self, num: usize) {
+        let num = num.clamp(1, MAX_PARTIALS);
+        let old = self.partials.len();
+        self.partials.resize_with(num, || Partial::new(0.0));
+        for (i, p) in self.partials.iter_mut().enumerate().skip(old) {
+            p.ratio = (i + 1) as f64;
+        }
+        self.update_rotations();
+    }
+
+    pub fn num_partials(&self) -> usize { self.partials.len() }
+
+    /// Sets the amplitude of partial `i`. Panics if `i` is out of range.
+    pub fn set_amp(&mut self, i: usize, amp: f64) { self.partials[i].amp = amp; }
+
+    /// Sets the frequency of partial `i`, as a ratio of the fundamental.
+    /// Panics if `i` is out of range.
+    pub fn set_ratio(&mut self, i: usize, ratio: f64) {
+        self.partials[i].ratio = ratio;
+        self.update_rotation(i);
+    }
+
+    /// Sets the phase of partial `i` in radians, right away and on `reset()`.
+    /// Panics if `i` is out of range.
+    pub fn set_phase(&mut self, i: usize, phase: f64) {
+        let p = &mut self.partials[i];
+        p.phase = phase;
+        p.re = phase.cos();
+        p.im = phase.sin();
+    }
+
+    /// Replaces all partials with harmonics, with the amplitudes in `amps`
+    /// starting from the fundamental, and zero phase. At most `MAX_PARTIALS`
+    /// amplitudes are used.
+    pub fn set_harmonics(&mut self, amps: &[f64]) {
+        self.partials = amps.iter().take(MAX_PARTIALS).enumerate().map(|(i, a)| {
+            let mut p = Partial::new((i + 1) as f64);
+            p.amp = *a;
+            p
+        }).collect();
+        if self.partials.is_empty() { self.partials.push(Partial::new(1.0)); }
+        self.update_rotations();
+    }
+
+    /// Loads the partials from an FFT frame of `spectrum.len()` samples, for
+    /// resynthesis. The fundamental is set to the spacing of the bins, and each
+    /// bin below nyquist becomes a harmonic partial, with the amplitude and
+    /// phase of the bin, so that the bank reproduces the analysed frame, and
+    /// repeats it periodically. The DC bin is dropped, and at most
+    /// `MAX_PARTIALS` bins are used.
+    ///
+    /// # Caveats
+    /// For an accurate resynthesis of the amplitudes, the frame should not be
+    /// windowed, or the spectrum should be compensated for the gain of the
+    /// window.
+    pub fn set_from_fft(&mut self, spectrum: &[Complex<f64>]) {
+        let n = spectrum.len();
+        let bins = n.saturating_sub(1) / 2;
+        self.freq = self.sr / n.max(1) as f64;
+        self.partials = spectrum.iter().skip(1).take(bins.min(MAX_PARTIALS)).enumerate()
+            .map(|(i, bin)| {
+                let mut p = Partial::new((i + 1) as f64);
+                p.amp = 2.0 * bin.norm() / n as f64;
+                // the bins are cosines, the partials are sines
+                p.phase = bin.arg() + consts::FRAC_PI_2;
+                p.re = p.phase.cos();
+                p.im = p.phase.sin();
+                p
+            })
+            .collect();
+        if self.partials.is_empty() { self.partials.push(Partial::new(1.0)); }
+        self.update_rotations();
+    }
+
+    /// Restarts all partials from their phases.
+    pub fn reset(&mut self) {
+        for p in self.partials.iter_mut() {
+            p.re = p.phase.cos();
+            p.im = p.phase.sin();
+        }
+    }
+
+    // recomputes the rotation and nyquist fade of partial `i`
+    fn update_rotation(&mut self, i: usize) {
+        let p = &mut self.partials[i];
+        let freq = self.freq * p.ratio;
+        let nyquist = 0.5 * self.sr;
+        p.gain = ((nyquist - freq.abs()) / (NYQUIST_FADE * nyquist)).clamp(0.0, 1.0);
+        let omega = consts::TAU * freq / self.sr;
+        p.rot_re = omega.cos();
+        p.rot_im = omega.sin();
+    }
+
+    fn update_rotations(&mut self) {
+        for i in 0..self.partials.len() { self.update_rotation(i); }
+    }
+}
+
+impl Source<f64> for AdditiveOsc {
+    fn step(&mut self) -> f64 {
+        let mut accum = 0.0;
+        for p in self.partials.iter_mut() {
+            accum += p.amp * p.gain * p.im;
+
+            let re = p.re * p.rot_re - p.im * p.rot_im;
+            let im = p.re * p.rot_im + p.im * p.rot_re;
+            // pulls the phasor back to the unit circle, rounding errors would
+            // otherwise make it slowly grow or decay
+            let norm = 1.5 - 0.5 * (re * re + im * im);
+            p.re = re * norm;
+            p.im = im * norm;
+        }
+        accum
+    }
+}
@@ -152,6 +152,8 @@ impl SnhRandom {
         }
     }
 
+    pub fn set_sr(&mut self, sr: f64) { self.sr = sr; }
+
     /// Change the frequency of the generator, in hertz. This is a method and
     /// not a field, because the frequency is stored internally as radians per second.
     pub fn set_freq(&mut self, freq: f64) {
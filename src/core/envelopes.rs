@@ -0,0 +1,133 @@
+//! Envelope generators.
+
+use crate::traits::Source;
+
+// overshoot of the exponential segments, relative to the distance they cover,
+// the attack overshoots more for a more linear, punchier curve
+const ATTACK_OVERSHOOT: f64 = 0.3;
+const DECAY_OVERSHOOT: f64 = 0.0001;
+
+// coefficient of a one-pole segment that covers its distance in `ms`, when
+// aiming `overshoot` past its end
+fn segment_coef(ms: f64, overshoot: f64, sr: f64) -> f64 {
+    let samples = (ms * 0.001 * sr).max(1.0);
+    (-((1.0 + overshoot) / overshoot).ln() / samples).exp()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Stage {
+    Idle,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+/// Attack, decay, sustain, release envelope, with analog style exponential
+/// segments.
+///
+/// - `attack`: time to rise from 0 to 1, in milliseconds
+/// - `decay`: time to fall from 1 to the sustain level, in milliseconds
+/// - `sustain`: level held while the note is on, between 0 and 1
+/// - `release`: time to fall from 1 to 0 after the note is off, in
+///   milliseconds, shorter when releasing from a lower level
+///
+/// Times can be changed at any moment, including during a segment.
+///
+/// # Examples
+/// ```
+/// use dsp_lab::core::envelopes::Adsr;
+/// use dsp_lab::traits::Source;
+/// let mut env = Adsr::new();
+/// env.attack = 5.0;
+/// env.note_on();
+/// for _ in 0..1000 { env.step(); }
+/// env.note_off();
+/// while env.is_active() { env.step(); }
+/// ```
+pub struct Adsr {
+    stage: Stage,
+    level: f64,
+    sr: f64,
+
+    pub attack: f64,
+    pub decay: f64,
+    pub sustain: f64,
+    pub release: f64,
+}
+
+impl Adsr {
+    /// Creates an envelope with 10ms attack, 200ms decay, 0.5 sustain and
+    /// 300ms release.
+    pub fn new() -> Self {
+        Self {
+            stage: Stage::Idle,
+            level: 0.0,
+            sr: 44100.0,
+
+            attack: 10.0,
+            decay: 200.0,
+            sustain: 0.5,
+            release: 300.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) { self.sr = sr; }
+
+    /// Starts the attack, from the current level, so that retriggering doesn't
+    /// click.
+    pub fn note_on(&mut self) { self.stage = Stage::Attack; }
+
+    /// Starts the release, from the current level.
+    pub fn note_off(&mut self) {
+        if self.stage != Stage::Idle { self.stage = Stage::Release; }
+    }
+
+    /// False once the release has ended.
+    pub fn is_active(&self) -> bool { self.stage != Stage::Idle }
+
+    /// Jumps back to 0, without release.
+    pub fn reset(&mut self) {
+        self.stage = Stage::Idle;
+        self.level = 0.0;
+    }
+}
+
+impl Source<f64> for Adsr {
+    fn step(&mut self) -> f64 {
+        let sustain = self.sustain.clamp(0.0, 1.0);
+        match self.stage {
+            Stage::Idle => {},
+            Stage::Attack => {
+                let target = 1.0 + ATTACK_OVERSHOOT;
+                let c = segment_coef(self.attack, ATTACK_OVERSHOOT, self.sr);
+                self.level = target + (self.level - target) * c;
+                if self.level >= 1.0 {
+                    self.level = 1.0;
+                    self.stage = Stage::Decay;
+                }
+            },
+            Stage::Decay => {
+                let target = sustain - DECAY_OVERSHOOT * (1.0 - sustain);
+                let c = segment_coef(self.decay, DECAY_OVERSHOOT, self.sr);
+                self.level = target + (self.level - target) * c;
+                if self.level <= sustain {
+                    self.level = sustain;
+                    self.stage = Stage::Sustain;
+                }
+            },
+            // follows changes of the sustain level
+            Stage::Sustain => self.level = sustain,
+            Stage::Release => {
+                let target = -DECAY_OVERSHOOT;
+                let c = segment_coef(self.release, DECAY_OVERSHOOT, self.sr);
+                self.level = target + (self.level - target) * c;
+                if self.level <= 0.0 {
+                    self.level = 0.0;
+                    self.stage = Stage::Idle;
+                }
+            },
+        }
+        self.level
+    }
+}
@@ -0,0 +1,254 @@
+//! Phase modulation (FM) synthesis, in the style of the Yamaha DX7.
+//!
+//! + `FmOperator`: sine oscillator with an envelope, whose phase is modulated
+//!   by its input and by its own output (feedback)
+//! + `FmVoice`: N operators, routed by a modulation matrix, playing one note
+//!
+//! Like in most "FM" synths, the operators actually use phase modulation, which
+//! doesn't detune the carrier when the modulator has a DC offset, and keeps
+//! feedback stable.
+
+use std::f64::consts;
+
+use crate::traits::{Process, Source};
+use crate::core::osc::RampCore;
+use crate::core::envelopes::Adsr;
+use crate::utils::math::par_shaper;
+
+/// Used to select how the frequency of an operator is set.
+///
+/// - Ratio: multiple of the frequency of the note
+/// - Fixed: frequency in hertz, regardless of the note, for inharmonic and
+///   percussive sounds
+#[derive(Clone, Copy, PartialEq)]
+pub enum OpFreq {
+    Ratio(f64),
+    Fixed(f64),
+}
+
+/// Used to select the waveform of an operator.
+///
+/// - Sine: exact sine
+/// - Parabolic: parabolic sine approximation, see `utils::math::par_shaper`,
+///   faster, with a few extra odd harmonics
+#[derive(Clone, Copy, PartialEq)]
+pub enum OpShape {
+    Sine,
+    Parabolic,
+}
+
+/// Phase modulation operator.
+///
+/// The input is the phase modulation, in radians, and the output is scaled by
+/// the envelope and by `level`, so that the level of a modulator is also its
+/// modulation index.
+///
+/// - `freq_mode`: ratio or fixed frequency
+/// - `shape`: waveform
+/// - `feedback`: self modulation, in radians, from 0 (sine) to around 1.5
+///   (close to a sawtooth), higher values turn into noise
+/// - `level`: output gain
+/// - `env`: envelope of the output
+///
+/// # Examples
+/// A two operator bell:
+/// ```
+/// use dsp_lab::core::fm::{FmOperator, OpFreq};
+/// use dsp_lab::traits::Process;
+/// let mut modulator = FmOperator::new();
+/// modulator.freq_mode = OpFreq::Ratio(3.5);
+/// modulator.level = 2.0;
+/// let mut carrier = FmOperator::new();
+/// modulator.set_freq(220.0);
+/// carrier.set_freq(220.0);
+/// modulator.env.note_on();
+/// carrier.env.note_on();
+/// let y = carrier.step(modulator.step(0.0));
+/// ```
+pub struct FmOperator {
+    osc: RampCore,
+    note_freq: f64,
+    // last two outputs, averaged for the feedback like in the DX7, which
+    // tames its tendency to oscillate at nyquist
+    y_z1: f64,
+    y_z2: f64,
+
+    pub freq_mode: OpFreq,
+    pub shape: OpShape,
+    pub feedback: f64,
+    pub level: f64,
+    pub env: Adsr,
+}
+
+impl FmOperator {
+    /// Creates an operator at a ratio of 1, with a sine, no feedback and unit
+    /// level.
+    pub fn new() -> Self {
+        Self {
+            osc: RampCore::new(),
+            note_freq: 440.0,
+            y_z1: 0.0,
+            y_z2: 0.0,
+
+            freq_mode: OpFreq::Ratio(1.0),
+            shape: OpShape::Sine,
+            feedback: 0.0,
+            level: 1.0,
+            env: Adsr::new(),
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        self.osc.sr = sr;
+        self.env.set_sr(sr);
+    }
+
+    /// Sets the frequency of the note, in hertz, which is multiplied by the
+    /// ratio, or ignored in fixed frequency mode. This is a method and not a
+    /// field, because the frequency mode can change after it is set.
+    pub fn set_freq(&mut self, freq: f64) { self.note_freq = freq; }
+
+    pub fn set_phase(&mut self, phase: f64) { self.osc.set_phase(phase); }
+}
+
+impl Process<f64> for FmOperator {
+    fn step(&mut self, input: f64) -> f64 {
+        self.osc.set_freq(match self.freq_mode {
+            OpFreq::Ratio(ratio) => self.note_freq * ratio,
+            OpFreq::Fixed(freq) => freq,
+        });
+
+        let fb = self.feedback * 0.5 * (self.y_z1 + self.y_z2);
+        let phase = (self.osc.step() + input + fb).rem_euclid(consts::TAU);
+        let y = match self.shape {
+            OpShape::Sine => phase.sin(),
+            OpShape::Parabolic => par_shaper(phase),
+        } * self.env.step() * self.level;
+
+        self.y_z2 = self.y_z1;
+        self.y_z1 = y;
+        y
+    }
+}
+
+
+/// Preset routings for `FmVoice`.
+///
+/// - Stack: each operator modulates the previous one, the first operator is
+///   the only carrier
+/// - Pairs: odd operators modulate the even operator before them, which are the
+///   carriers
+/// - Parallel: no modulation, all operators are carriers, for additive sounds
+#[derive(Clone, Copy, PartialEq)]
+pub enum FmAlgorithm {
+    Stack,
+    Pairs,
+    Parallel,
+}
+
+/// FM voice with `N` operators.
+///
+/// - `ops`: the operators, each with its own frequency, level and envelope
+/// - `routing`: modulation matrix, `routing[i][j]` is how much operator `j`
+///   modulates operator `i`
+/// - `mix`: output gain of each operator, operators with a non-zero gain are
+///   carriers
+///
+/// Operators are computed from the last to the first, like in the DX7 where
+/// operator 1 is a carrier and operator 6 is at the top of the stacks, so
+/// modulation from an operator to one with a lower index is immediate, while
+/// modulation to one with a higher (or the same) index is delayed by a sample.
+///
+/// # Examples
+/// A 4 operator electric piano, made of two stacks:
+/// ```
+/// use dsp_lab::core::fm::{FmVoice, FmAlgorithm, OpFreq};
+/// use dsp_lab::traits::Source;
+/// let mut voice = FmVoice::<4>::new();
+/// voice.set_algorithm(FmAlgorithm::Pairs);
+/// voice.ops[1].freq_mode = OpFreq::Ratio(14.0);
+/// voice.ops[1].level = 0.8;
+/// voice.ops[3].level = 1.5;
+/// voice.note_on(261.6);
+/// let y = voice.step();
+/// ```
+pub struct FmVoice<const N: usize> {
+    outputs: [f64; N],
+
+    pub ops: [FmOperator; N],
+    pub routing: [[f64; N]; N],
+    pub mix: [f64; N],
+}
+
+impl<const N: usize> FmVoice<N> {
+    /// Creates a voice with the `Stack` algorithm.
+    pub fn new() -> Self {
+        let mut ret = Self {
+            outputs: [0.0; N],
+
+            ops: std::array::from_fn(|_| FmOperator::new()),
+            routing: [[0.0; N]; N],
+            mix: [0.0; N],
+        };
+        ret.set_algorithm(FmAlgorithm::Stack);
+        ret
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        for op in self.ops.iter_mut() { op.set_sr(sr); }
+    }
+
+    /// Overwrites `routing` and `mix` with a preset, carriers are mixed at
+    /// equal gain, with a total gain of 1.
+    pub fn set_algorithm(&mut self, algorithm: FmAlgorithm) {
+        self.routing = [[0.0; N]; N];
+        self.mix = [0.0; N];
+        for i in 0..N {
+            match algorithm {
+                FmAlgorithm::Stack => {
+                    if i + 1 < N { self.routing[i][i + 1] = 1.0; }
+                    if i == 0 { self.mix[i] = 1.0; }
+                },
+                FmAlgorithm::Pairs => {
+                    if i % 2 == 0 {
+                        if i + 1 < N { self.routing[i][i + 1] = 1.0; }
+                        self.mix[i] = 1.0;
+                    }
+                },
+                FmAlgorithm::Parallel => self.mix[i] = 1.0,
+            }
+        }
+        let carriers = self.mix.iter().filter(|g| **g != 0.0).count().max(1);
+        for g in self.mix.iter_mut() { *g /= carriers as f64; }
+    }
+
+    /// Starts a note at `freq` hertz, triggering all envelopes.
+    pub fn note_on(&mut self, freq: f64) {
+        for op in self.ops.iter_mut() {
+            op.set_freq(freq);
+            op.env.note_on();
+        }
+    }
+
+    /// Releases all envelopes.
+    pub fn note_off(&mut self) {
+        for op in self.ops.iter_mut() { op.env.note_off(); }
+    }
+
+    /// False once the envelopes of all carriers have ended.
+    pub fn is_active(&self) -> bool {
+        self.ops.iter().zip(self.mix.iter()).any(|(op, g)| *g != 0.0 && op.env.is_active())
+    }
+}
+
+impl<const N: usize> Source<f64> for FmVoice<N> {
+    fn step(&mut self) -> f64 {
+        for i in (0..N).rev() {
+            let pm: f64 = self.routing[i].iter().zip(self.outputs.iter())
+                .map(|(amount, y)| amount * y)
+                .sum();
+            self.outputs[i] = self.ops[i].step(pm);
+        }
+        self.outputs.iter().zip(self.mix.iter()).map(|(y, g)| y * g).sum()
+    }
+}
@@ -1,8 +1,12 @@
 //! Linear filters.
 //! 
-//! + 1-pole high-pass and low-pass topologies
+//! + 1-pole high-pass, low-pass and all-pass topologies
 //! + 2-pole filters, based on an Svf core
 //! + Generic FIR filters   TODO:
+//! + All-pass and FIR Hilbert transformers
+//! + Partitioned FFT convolution, in the `convolution` submodule
+
+pub mod convolution;
 
 use std::f64::consts;
 
@@ -22,7 +26,7 @@ use crate::utils::conversion::{f_to_omega, r_to_q, db_to_gain};
 /// This is not sample-rate aware, i.e. it does not scale the volume, i.e. it is
 /// not a derivative.
 #[deprecated(since="0.2.0", note="Deprecated since it is not sample rate aware.
-Use DiffFwd, DiffC or  instead.")]
+Use DiffFwd, DiffC or DiffHQ instead.")]
 pub struct Diff { z1: f64 }
 
 impl Diff {
@@ -80,9 +84,156 @@ impl Process<f64> for DiffFwd {
 }
 
 
-// TODO: DiffC for centered finite differentiation
+/// Centered finite differentiator.
+/// 
+/// Computes the derivative as the slope between the previous and the next sample,
+/// so the derivative is evaluated one sample in the past.
+/// 
+/// The frequency response is `sin(omega)`, so it follows the ideal derivative 
+/// at low frequencies, peaks at fs/4 and goes back down to zero at nyquist.
+/// This attenuates high-frequency noise, which makes it a better choice than
+/// `DiffFwd` for real-world audio.
+/// 
+/// # Caveats
+/// Has a latency of 1 sample.
+pub struct DiffC {
+    x_z1: f64,
+    x_z2: f64,
+    sr_scale: f64,
+}
+
+impl DiffC {
+    pub fn new() -> Self {
+        Self {
+            x_z1: 0.0,
+            x_z2: 0.0,
+            sr_scale: 1.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        self.sr_scale = sr / 44100.0;
+    }
+
+    /// Latency in samples.
+    pub fn latency(&self) -> usize { 1 }
+}
+
+impl Process<f64> for DiffC {
+    fn step(&mut self, input: f64) -> f64 {
+        let ret = (input - self.x_z2) * 0.5 * self.sr_scale;
+        self.x_z2 = self.x_z1;
+        self.x_z1 = input;
+        ret
+    }
+}
+
+
+// half-length of the DiffHQ kernel, the kernel has 2 * DIFF_HQ_LEN + 1 taps
+const DIFF_HQ_LEN: usize = 16;
 
-// TODO: DiffHQ for minimizing equation error
+// bandwidth over which the DiffHQ kernel is optimized, as a fraction of nyquist
+const DIFF_HQ_BW: f64 = 0.8;
+
+/// High accuracy FIR differentiator, for accurate numeric derivatives.
+/// 
+/// Uses an antisymmetric 33-tap kernel, with coefficients chosen to minimize
+/// the squared relative equation error with respect to the ideal derivative, 
+/// from DC up to 80% of nyquist. Above that, the response quickly falls to zero 
+/// at nyquist.
+/// 
+/// The frequency response is linear phase, with a magnitude within 0.01% of the 
+/// ideal derivative up to 80% of nyquist.
+/// 
+/// # Caveats
+/// Has a latency of 16 samples.
+pub struct DiffHQ {
+    x: [f64; 2 * DIFF_HQ_LEN + 1],
+    coeffs: [f64; DIFF_HQ_LEN],
+    sr_scale: f64,
+}
+
+impl DiffHQ {
+    pub fn new() -> Self {
+        Self {
+            x: [0.0; 2 * DIFF_HQ_LEN + 1],
+            coeffs: Self::design(),
+            sr_scale: 1.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        self.sr_scale = sr / 44100.0;
+    }
+
+    /// Latency in samples.
+    pub fn latency(&self) -> usize { DIFF_HQ_LEN }
+
+    // Least-squares design of the kernel. The response of the kernel is
+    // 2 * sum(c_k * sin(k * w)), which is fitted to w over [0, bw * PI], weighting
+    // the error by 1 / w so that the relative error is minimized. The normal
+    // equations are integrated numerically and solved with gaussian elimination.
+    fn design() -> [f64; DIFF_HQ_LEN] {
+        const N: usize = DIFF_HQ_LEN;
+        const STEPS: usize = 1024;
+        let w_max = DIFF_HQ_BW * consts::PI;
+        let mut a = [[0.0; N]; N];
+        let mut b = [0.0; N];
+
+        // midpoint rule integration
+        let mut basis = [0.0; N];
+        for m in 0..STEPS {
+            let w = (m as f64 + 0.5) * w_max / STEPS as f64;
+            for (k, s) in basis.iter_mut().enumerate() {
+                *s = 2.0 * ((k + 1) as f64 * w).sin() / w;
+            }
+            for j in 0..N {
+                b[j] += basis[j];
+                for k in 0..N {
+                    a[j][k] += basis[j] * basis[k];
+                }
+            }
+        }
+
+        // forward elimination, the matrix is symmetric positive definite so
+        // no pivoting is needed.
+        for i in 0..N {
+            let pivot = a[i];
+            for r in (i + 1)..N {
+                let f = a[r][i] / pivot[i];
+                for (x, p) in a[r][i..].iter_mut().zip(&pivot[i..]) {
+                    *x -= f * p;
+                }
+                b[r] -= f * b[i];
+            }
+        }
+
+        // back substitution
+        let mut coeffs = [0.0; N];
+        for i in (0..N).rev() {
+            let mut acc = b[i];
+            for c in (i + 1)..N {
+                acc -= a[i][c] * coeffs[c];
+            }
+            coeffs[i] = acc / a[i][i];
+        }
+        coeffs
+    }
+}
+
+impl Process<f64> for DiffHQ {
+    fn step(&mut self, input: f64) -> f64 {
+        self.x.copy_within(0..2 * DIFF_HQ_LEN, 1);
+        self.x[0] = input;
+
+        // antisymmetric kernel centered on x[DIFF_HQ_LEN]
+        let mut accum = 0.0;
+        for (k, c) in self.coeffs.iter().enumerate() {
+            accum += c * (self.x[DIFF_HQ_LEN - k - 1] - self.x[DIFF_HQ_LEN + k + 1]);
+        }
+        accum * self.sr_scale
+    }
+}
 
 
 // TODO: replace with sample-rate aware leaky int
@@ -122,7 +273,7 @@ impl Process<f64> for LeakyInt {
 /// * Bad numerical precision, for approximating integrals use `IntegTrpz` or
 /// `IntegRK4`.
 /// * Will overflow with DC signals, for overflow protection use `IntegLeaky` or
-/// `IntegSafe` or remove DC signals with `BlockDC`.
+/// `IntegSafe` or remove DC signals with `DcBlock`.
 pub struct Integ {
     y_z1: f64,
     inv_sr_scale: f64,
@@ -149,36 +300,211 @@ impl Process<f64> for Integ {
 }
 
 
+/// Leaky integrator, the integral slowly decays back towards zero, which
+/// prevents DC signals from making it overflow.
+/// 
+/// The leak is set as a time constant in milliseconds, which is the time it
+/// takes for the state to decay by a factor of `e` with no input. The default
+/// is roughly 227ms.
+/// 
+/// # Caveats
+/// * Frequencies below `1 / (TAU * leak)` Hz are not integrated, but just 
+///   passed through with a fixed gain.
 pub struct IntegLeaky {
     y_z1: f64,
     inv_sr_scale: f64,
+    pole: f64,
+    leak_ms: f64,
+    sr: f64,
 }
 
 impl IntegLeaky {
+    pub fn new() -> Self {
+        let mut ret = Self {
+            y_z1: 0.0,
+            inv_sr_scale: 1.0,
+            pole: 0.0,
+            leak_ms: 226.75,
+            sr: 44100.0,
+        };
+        ret.update_pole();
+        ret
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        self.inv_sr_scale = 44100.0 / sr;
+        self.sr = sr;
+        self.update_pole();
+    }
+
+    /// Set the time constant of the leak, in milliseconds.
+    pub fn set_leak(&mut self, leak_ms: f64) {
+        self.leak_ms = leak_ms;
+        self.update_pole();
+    }
+
+    fn update_pole(&mut self) {
+        let tau_samples = (self.leak_ms * 0.001 * self.sr).max(1e-30);
+        self.pole = (-1.0 / tau_samples).exp();
+    }
+}
+
+impl Process<f64> for IntegLeaky {
+    fn step(&mut self, input: f64) -> f64 {
+        self.y_z1 = self.inv_sr_scale * input + self.y_z1 * self.pole;
+        self.y_z1
+    }
+}
+
+
+/// Used to select what `IntegSafe` does when the state exceeds its limit.
+/// 
+/// - Clamp: the state is held at the limit until the input pulls it back
+/// - Reset: the state is reset to zero
+/// - Wrap: the state wraps around to the opposite limit, useful for integrating
+///   frequency into phase
+pub enum OverflowMode {
+    Clamp,
+    Reset,
+    Wrap,
+}
+
+/// Overflow protected integrator.
+/// 
+/// Like `Integ`, but the state is kept within `[-limit, limit]`, what happens
+/// when the limit is exceeded is selected with `overflow_mode`. Non-finite 
+/// states (NaN or infinity) always reset the integrator.
+pub struct IntegSafe {
+    y_z1: f64,
+    inv_sr_scale: f64,
+    pub limit: f64,
+    pub overflow_mode: OverflowMode,
+}
+
+impl IntegSafe {
     pub fn new() -> Self {
         Self {
             y_z1: 0.0,
             inv_sr_scale: 1.0,
+            limit: 1.0,
+            overflow_mode: OverflowMode::Clamp,
         }
     }
 
     pub fn set_sr(&mut self, sr: f64) {
         self.inv_sr_scale = 44100.0 / sr;
     }
+
+    /// Resets the state of the integrator to zero.
+    pub fn reset(&mut self) { self.y_z1 = 0.0; }
 }
 
-impl Process<f64> for IntegLeaky {
+impl Process<f64> for IntegSafe {
+    fn step(&mut self, input: f64) -> f64 {
+        let limit = self.limit.abs();
+        let y = self.y_z1 + self.inv_sr_scale * input;
+
+        self.y_z1 = if !y.is_finite() {
+            0.0
+        } else if y.abs() <= limit {
+            y
+        } else {
+            match self.overflow_mode {
+                OverflowMode::Clamp => y.clamp(-limit, limit),
+                OverflowMode::Reset => 0.0,
+                OverflowMode::Wrap  => {
+                    if limit == 0.0 { 0.0 } 
+                    else { (y + limit).rem_euclid(2.0 * limit) - limit }
+                },
+            }
+        };
+        self.y_z1
+    }
+}
+
+
+/// Trapezoidal rule integrator.
+/// 
+/// Integrates the straight line between consecutive samples, rather than
+/// treating each sample as a step like `Integ`. This is second order accurate,
+/// and its response has no phase error, at the cost of a zero at nyquist.
+/// 
+/// # Caveats
+/// * Will overflow with DC signals, like `Integ`.
+pub struct IntegTrpz {
+    x_z1: f64,
+    y_z1: f64,
+    inv_sr_scale: f64,
+}
+
+impl IntegTrpz {
+    pub fn new() -> Self {
+        Self {
+            x_z1: 0.0,
+            y_z1: 0.0,
+            inv_sr_scale: 1.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        self.inv_sr_scale = 44100.0 / sr;
+    }
+}
+
+impl Process<f64> for IntegTrpz {
     fn step(&mut self, input: f64) -> f64 {
-        self.y_z1 = self.inv_sr_scale * input + self.y_z1 * 0.9999;
+        self.y_z1 += self.inv_sr_scale * 0.5 * (input + self.x_z1);
+        self.x_z1 = input;
         self.y_z1
     }
 }
 
 
-// TODO: IntegLeaky leaky integrator
-// TODO: IntegSafe  overflow protected integrator
-// TODO: IntegTrpz  trapezoidal rule integrator
-// TODO: IntegRK4   4th-order runge kutta integrator
+/// 4th-order Runge-Kutta integrator.
+/// 
+/// For a signal that doesn't depend on the state of the integrator, an RK4 step
+/// reduces to Simpson's rule, where the midpoint between samples is estimated
+/// with cubic interpolation over the last four samples. This is the most
+/// accurate integrator in this module, but also the most expensive.
+/// 
+/// # Caveats
+/// * Will overflow with DC signals, like `Integ`.
+pub struct IntegRK4 {
+    x_z1: f64,
+    x_z2: f64,
+    x_z3: f64,
+    y_z1: f64,
+    inv_sr_scale: f64,
+}
+
+impl IntegRK4 {
+    pub fn new() -> Self {
+        Self {
+            x_z1: 0.0,
+            x_z2: 0.0,
+            x_z3: 0.0,
+            y_z1: 0.0,
+            inv_sr_scale: 1.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        self.inv_sr_scale = 44100.0 / sr;
+    }
+}
+
+impl Process<f64> for IntegRK4 {
+    fn step(&mut self, input: f64) -> f64 {
+        // lagrange interpolation halfway between x_z1 and input
+        let x_mid = (5.0 * input + 15.0 * self.x_z1 - 5.0 * self.x_z2 + self.x_z3) * 0.0625;
+        self.y_z1 += self.inv_sr_scale * (self.x_z1 + 4.0 * x_mid + input) / 6.0;
+
+        self.x_z3 = self.x_z2;
+        self.x_z2 = self.x_z1;
+        self.x_z1 = input;
+        self.y_z1
+    }
+}
 
 
 // === SVF CORE 2-POLE FILTERS ===
@@ -415,8 +741,10 @@ impl DcBlock {
         ret
     }
 
+    /// Sets the sample rate, keeping the cutoff at 10Hz.
     pub fn set_sr(&mut self, sr: f64) {
         self.lp.set_sr(sr);
+        self.lp.set_cutoff(10.0);
     }
 }
 
@@ -428,6 +756,49 @@ impl Process<f64> for DcBlock {
 }
 
 
+/// First order all-pass, shifts the phase by 90 degrees at the cutoff, going
+/// from 0 degrees at DC to 180 degrees at nyquist. Cheaper and with a gentler
+/// phase response than `BiquadAllPass`, it is the classic phaser stage.
+pub struct AllPass1P {
+    x_z1: f64,
+    y_z1: f64,
+    pub cutoff: f64,
+    pub sr: f64,
+}
+
+impl AllPass1P {
+    pub fn new() -> Self {
+        Self {
+            x_z1: 0.0,
+            y_z1: 0.0,
+            cutoff: 440.0,
+            sr: 44100.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) { self.sr = sr; }
+
+    /// Returns the filter coefficient `c` for the current cutoff, the transfer
+    /// function being `(c + z^-1) / (1 + c z^-1)`.
+    pub fn coeff(&self) -> f64 {
+        // clamp cutoff just below nyquist, where the tangent blows up
+        let f = self.cutoff.clamp(0.0, self.sr * 0.499);
+        let t = (consts::PI * f / self.sr).tan();
+        (t - 1.0) / (t + 1.0)
+    }
+}
+
+impl Process<f64> for AllPass1P {
+    fn step(&mut self, input: f64) -> f64 {
+        let c = self.coeff();
+        let y = c * input + self.x_z1 - c * self.y_z1;
+        self.x_z1 = input;
+        self.y_z1 = y;
+        y
+    }
+}
+
+
 // === BIQUAD 2-POLE FILTERS ===
 
 struct BiquadCore {
@@ -479,6 +850,30 @@ pub struct BiquadLowPass {
 
 impl Process<f64> for BiquadLowPass {
     fn step(&mut self, input: f64) -> f64 {
+        let (a, b) = self.coeffs();
+        self.core.filter(input, a, b)
+    }
+}
+
+impl BiquadLowPass {
+    pub fn new() -> Self {
+        Self {
+            core: BiquadCore::new(),
+            cutoff: 440.0,
+            q: 0.707,
+            sr: 44100.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) { self.sr = sr; }
+
+    /// Allows to set the Q-factor by giving a resonance parameter between 0 and 1
+    /// resembling the resonance setting of an analog filter.
+    pub fn set_res(&mut self, res: f64) { self.q = r_to_q(res) + 0.01; }
+
+    /// Returns the filter coefficients `(a, b)` for the current parameters,
+    /// where `a` are the feedback and `b` the feedforward coefficients.
+    pub fn coeffs(&self) -> ([f64; 3], [f64; 3]) {
         // clamp cutoff at nyquist
         let f = self.cutoff.clamp(0.0, self.sr/2.0);
         let omega = f_to_omega(f, self.sr);
@@ -493,11 +888,25 @@ impl Process<f64> for BiquadLowPass {
         let a_1 = -2.0 * c;
         let a_2 = 1.0 - alpha;
 
-        self.core.filter(input, [a_0, a_1, a_2], [b_0, b_1, b_2])
+        ([a_0, a_1, a_2], [b_0, b_1, b_2])
     }
 }
 
-impl BiquadLowPass {
+pub struct BiquadHighPass {
+    core: BiquadCore,
+    pub cutoff: f64,
+    pub q: f64,
+    pub sr: f64,
+}
+
+impl Process<f64> for BiquadHighPass {
+    fn step(&mut self, input: f64) -> f64 {
+        let (a, b) = self.coeffs();
+        self.core.filter(input, a, b)
+    }
+}
+
+impl BiquadHighPass {
     pub fn new() -> Self {
         Self {
             core: BiquadCore::new(),
@@ -512,17 +921,10 @@ impl BiquadLowPass {
     /// Allows to set the Q-factor by giving a resonance parameter between 0 and 1
     /// resembling the resonance setting of an analog filter.
     pub fn set_res(&mut self, res: f64) { self.q = r_to_q(res) + 0.01; }
-}
 
-pub struct BiquadHighPass {
-    core: BiquadCore,
-    pub cutoff: f64,
-    pub q: f64,
-    pub sr: f64,
-}
-
-impl Process<f64> for BiquadHighPass {
-    fn step(&mut self, input: f64) -> f64 {
+    /// Returns the filter coefficients `(a, b)` for the current parameters,
+    /// where `a` are the feedback and `b` the feedforward coefficients.
+    pub fn coeffs(&self) -> ([f64; 3], [f64; 3]) {
         // clamp cutoff at nyquist
         let f = self.cutoff.clamp(0.0, self.sr/2.0);
         let omega = f_to_omega(f, self.sr);
@@ -537,11 +939,25 @@ impl Process<f64> for BiquadHighPass {
         let a_1 = -2.0 * c;
         let a_2 = 1.0 - alpha;
 
-        self.core.filter(input, [a_0, a_1, a_2], [b_0, b_1, b_2])
+        ([a_0, a_1, a_2], [b_0, b_1, b_2])
     }
 }
 
-impl BiquadHighPass {
+pub struct BiquadBandPass {
+    core: BiquadCore,
+    pub cutoff: f64,
+    pub q: f64,
+    pub sr: f64,
+}
+
+impl Process<f64> for BiquadBandPass {
+    fn step(&mut self, input: f64) -> f64 {
+        let (a, b) = self.coeffs();
+        self.core.filter(input, a, b)
+    }
+}
+
+impl BiquadBandPass {
     pub fn new() -> Self {
         Self {
             core: BiquadCore::new(),
@@ -556,17 +972,10 @@ impl BiquadHighPass {
     /// Allows to set the Q-factor by giving a resonance parameter between 0 and 1
     /// resembling the resonance setting of an analog filter.
     pub fn set_res(&mut self, res: f64) { self.q = r_to_q(res) + 0.01; }
-}
 
-pub struct BiquadBandPass {
-    core: BiquadCore,
-    pub cutoff: f64,
-    pub q: f64,
-    pub sr: f64,
-}
-
-impl Process<f64> for BiquadBandPass {
-    fn step(&mut self, input: f64) -> f64 {
+    /// Returns the filter coefficients `(a, b)` for the current parameters,
+    /// where `a` are the feedback and `b` the feedforward coefficients.
+    pub fn coeffs(&self) -> ([f64; 3], [f64; 3]) {
         // clamp cutoff at nyquist
         let f = self.cutoff.clamp(0.0, self.sr/2.0);
         let omega = f_to_omega(f, self.sr);
@@ -581,11 +990,25 @@ impl Process<f64> for BiquadBandPass {
         let a_1 = -2.0 * c;
         let a_2 = 1.0 - alpha;
 
-        self.core.filter(input, [a_0, a_1, a_2], [b_0, b_1, b_2])
+        ([a_0, a_1, a_2], [b_0, b_1, b_2])
     }
 }
 
-impl BiquadBandPass {
+pub struct BiquadNotch {
+    core: BiquadCore,
+    pub cutoff: f64,
+    pub q: f64,
+    pub sr: f64,
+}
+
+impl Process<f64> for BiquadNotch {
+    fn step(&mut self, input: f64) -> f64 {
+        let (a, b) = self.coeffs();
+        self.core.filter(input, a, b)
+    }
+}
+
+impl BiquadNotch {
     pub fn new() -> Self {
         Self {
             core: BiquadCore::new(),
@@ -600,17 +1023,10 @@ impl BiquadBandPass {
     /// Allows to set the Q-factor by giving a resonance parameter between 0 and 1
     /// resembling the resonance setting of an analog filter.
     pub fn set_res(&mut self, res: f64) { self.q = r_to_q(res) + 0.01; }
-}
-
-pub struct BiquadNotch {
-    core: BiquadCore,
-    pub cutoff: f64,
-    pub q: f64,
-    pub sr: f64,
-}
 
-impl Process<f64> for BiquadNotch {
-    fn step(&mut self, input: f64) -> f64 {
+    /// Returns the filter coefficients `(a, b)` for the current parameters,
+    /// where `a` are the feedback and `b` the feedforward coefficients.
+    pub fn coeffs(&self) -> ([f64; 3], [f64; 3]) {
         // clamp cutoff at nyquist
         let f = self.cutoff.clamp(0.0, self.sr/2.0);
         let omega = f_to_omega(f, self.sr);
@@ -625,11 +1041,25 @@ impl Process<f64> for BiquadNotch {
         let a_1 = -2.0 * c;
         let a_2 = 1.0 - alpha;
 
-        self.core.filter(input, [a_0, a_1, a_2], [b_0, b_1, b_2])
+        ([a_0, a_1, a_2], [b_0, b_1, b_2])
     }
 }
 
-impl BiquadNotch {
+pub struct BiquadAllPass {
+    core: BiquadCore,
+    pub cutoff: f64,
+    pub q: f64,
+    pub sr: f64,
+}
+
+impl Process<f64> for BiquadAllPass {
+    fn step(&mut self, input: f64) -> f64 {
+        let (a, b) = self.coeffs();
+        self.core.filter(input, a, b)
+    }
+}
+
+impl BiquadAllPass {
     pub fn new() -> Self {
         Self {
             core: BiquadCore::new(),
@@ -644,17 +1074,10 @@ impl BiquadNotch {
     /// Allows to set the Q-factor by giving a resonance parameter between 0 and 1
     /// resembling the resonance setting of an analog filter.
     pub fn set_res(&mut self, res: f64) { self.q = r_to_q(res) + 0.01; }
-}
 
-pub struct BiquadAllPass {
-    core: BiquadCore,
-    pub cutoff: f64,
-    pub q: f64,
-    pub sr: f64,
-}
-
-impl Process<f64> for BiquadAllPass {
-    fn step(&mut self, input: f64) -> f64 {
+    /// Returns the filter coefficients `(a, b)` for the current parameters,
+    /// where `a` are the feedback and `b` the feedforward coefficients.
+    pub fn coeffs(&self) -> ([f64; 3], [f64; 3]) {
         // clamp cutoff at nyquist
         let f = self.cutoff.clamp(0.0, self.sr/2.0);
         let omega = f_to_omega(f, self.sr);
@@ -669,17 +1092,33 @@ impl Process<f64> for BiquadAllPass {
         let a_1 = -2.0 * c;
         let a_2 = 1.0 - alpha;
 
-        self.core.filter(input, [a_0, a_1, a_2], [b_0, b_1, b_2])
+        ([a_0, a_1, a_2], [b_0, b_1, b_2])
     }
 }
 
-impl BiquadAllPass {
+pub struct BiquadPeaking {
+    core: BiquadCore,
+    pub cutoff: f64,
+    pub q: f64,
+    pub sr: f64,
+    pub db_gain: f64,
+}
+
+impl Process<f64> for BiquadPeaking {
+    fn step(&mut self, input: f64) -> f64 {
+        let (a, b) = self.coeffs();
+        self.core.filter(input, a, b)
+    }
+}
+
+impl BiquadPeaking {
     pub fn new() -> Self {
         Self {
             core: BiquadCore::new(),
             cutoff: 440.0,
             q: 0.707,
             sr: 44100.0,
+            db_gain: 0.0,
         }
     }
 
@@ -688,18 +1127,10 @@ impl BiquadAllPass {
     /// Allows to set the Q-factor by giving a resonance parameter between 0 and 1
     /// resembling the resonance setting of an analog filter.
     pub fn set_res(&mut self, res: f64) { self.q = r_to_q(res) + 0.01; }
-}
-
-pub struct BiquadPeaking {
-    core: BiquadCore,
-    pub cutoff: f64,
-    pub q: f64,
-    pub sr: f64,
-    pub db_gain: f64,
-}
 
-impl Process<f64> for BiquadPeaking {
-    fn step(&mut self, input: f64) -> f64 {
+    /// Returns the filter coefficients `(a, b)` for the current parameters,
+    /// where `a` are the feedback and `b` the feedforward coefficients.
+    pub fn coeffs(&self) -> ([f64; 3], [f64; 3]) {
         // clamp cutoff at nyquist
         let f = self.cutoff.clamp(0.0, self.sr/2.0);
         let omega = f_to_omega(f, self.sr);
@@ -715,11 +1146,26 @@ impl Process<f64> for BiquadPeaking {
         let a_1 = -2.0 * c;
         let a_2 = 1.0 - alpha / amp;
 
-        self.core.filter(input, [a_0, a_1, a_2], [b_0, b_1, b_2])
+        ([a_0, a_1, a_2], [b_0, b_1, b_2])
     }
 }
 
-impl BiquadPeaking {
+pub struct BiquadLowShelf {
+    core: BiquadCore,
+    pub cutoff: f64,
+    pub q: f64,
+    pub sr: f64,
+    pub db_gain: f64,
+}
+
+impl Process<f64> for BiquadLowShelf {
+    fn step(&mut self, input: f64) -> f64 {
+        let (a, b) = self.coeffs();
+        self.core.filter(input, a, b)
+    }
+}
+
+impl BiquadLowShelf {
     pub fn new() -> Self {
         Self {
             core: BiquadCore::new(),
@@ -735,18 +1181,10 @@ impl BiquadPeaking {
     /// Allows to set the Q-factor by giving a resonance parameter between 0 and 1
     /// resembling the resonance setting of an analog filter.
     pub fn set_res(&mut self, res: f64) { self.q = r_to_q(res) + 0.01; }
-}
-
-pub struct BiquadLowShelf {
-    core: BiquadCore,
-    pub cutoff: f64,
-    pub q: f64,
-    pub sr: f64,
-    pub db_gain: f64,
-}
 
-impl Process<f64> for BiquadLowShelf {
-    fn step(&mut self, input: f64) -> f64 {
+    /// Returns the filter coefficients `(a, b)` for the current parameters,
+    /// where `a` are the feedback and `b` the feedforward coefficients.
+    pub fn coeffs(&self) -> ([f64; 3], [f64; 3]) {
         // clamp cutoff at nyquist
         let f = self.cutoff.clamp(0.0, self.sr/2.0);
         let omega = f_to_omega(f, self.sr);
@@ -763,11 +1201,26 @@ impl Process<f64> for BiquadLowShelf {
         let a_1 = -2.0 * ((amp - 1.0) + (amp + 1.0) * c);
         let a_2 = (amp + 1.0) + (amp - 1.0) * c - aux_shelf;
 
-        self.core.filter(input, [a_0, a_1, a_2], [b_0, b_1, b_2])
+        ([a_0, a_1, a_2], [b_0, b_1, b_2])
     }
 }
 
-impl BiquadLowShelf {
+pub struct BiquadHighShelf {
+    core: BiquadCore,
+    pub cutoff: f64,
+    pub q: f64,
+    pub sr: f64,
+    pub db_gain: f64,
+}
+
+impl Process<f64> for BiquadHighShelf {
+    fn step(&mut self, input: f64) -> f64 {
+        let (a, b) = self.coeffs();
+        self.core.filter(input, a, b)
+    }
+}
+
+impl BiquadHighShelf {
     pub fn new() -> Self {
         Self {
             core: BiquadCore::new(),
@@ -783,18 +1236,10 @@ impl BiquadLowShelf {
     /// Allows to set the Q-factor by giving a resonance parameter between 0 and 1
     /// resembling the resonance setting of an analog filter.
     pub fn set_res(&mut self, res: f64) { self.q = r_to_q(res) + 0.01; }
-}
-
-pub struct BiquadHighShelf {
-    core: BiquadCore,
-    pub cutoff: f64,
-    pub q: f64,
-    pub sr: f64,
-    pub db_gain: f64,
-}
 
-impl Process<f64> for BiquadHighShelf {
-    fn step(&mut self, input: f64) -> f64 {
+    /// Returns the filter coefficients `(a, b)` for the current parameters,
+    /// where `a` are the feedback and `b` the feedforward coefficients.
+    pub fn coeffs(&self) -> ([f64; 3], [f64; 3]) {
         // clamp cutoff at nyquist
         let f = self.cutoff.clamp(0.0, self.sr/2.0);
         let omega = f_to_omega(f, self.sr);
@@ -811,26 +1256,124 @@ impl Process<f64> for BiquadHighShelf {
         let a_1 = -2.0 * ((amp - 1.0) - (amp + 1.0) * c);
         let a_2 = (amp + 1.0) - (amp - 1.0) * c - aux_shelf;
 
-        self.core.filter(input, [a_0, a_1, a_2], [b_0, b_1, b_2])
+        ([a_0, a_1, a_2], [b_0, b_1, b_2])
     }
 }
 
-impl BiquadHighShelf {
+
+// === HILBERT TRANSFORMERS ===
+
+// Coefficients of the two all-pass chains of HilbertAp, courtesy of Olli Niemitalo:
+// <https://yehar.com/blog/?p=368>
+const HILBERT_AP_RE: [f64; 4] = [0.4021921162426, 0.8561710882420, 0.9722909545651, 0.9952884791278];
+const HILBERT_AP_IM: [f64; 4] = [0.6923878, 0.9360654322959, 0.9882295226860, 0.9987488452737];
+
+/// All-pass pair Hilbert transformer, produces an analytic signal, i.e. a pair
+/// of outputs with a 90 degree phase difference, for frequency shifting and
+/// single-sideband modulation.
+/// 
+/// Uses two parallel chains of 2nd-order all-pass sections, the phase difference
+/// between the chains stays within 0.7 degrees of 90 degrees from 15Hz to 20kHz
+/// at 44100Hz sample rate. The magnitude response is perfectly flat, but the
+/// phase response is not linear, so the outputs are phase shifted with respect 
+/// to the input.
+/// 
+/// # Caveats
+/// The coefficients are designed for 44100Hz, at other sample rates the band
+/// of accurate phase difference scales with the sample rate.
+pub struct HilbertAp {
+    re_chain: [BiquadCore; 4],
+    im_chain: [BiquadCore; 4],
+    im_z1: f64,
+}
+
+impl HilbertAp {
     pub fn new() -> Self {
         Self {
-            core: BiquadCore::new(),
-            cutoff: 440.0,
-            q: 0.707,
-            sr: 44100.0,
-            db_gain: 0.0,
+            re_chain: [BiquadCore::new(), BiquadCore::new(), BiquadCore::new(), BiquadCore::new()],
+            im_chain: [BiquadCore::new(), BiquadCore::new(), BiquadCore::new(), BiquadCore::new()],
+            im_z1: 0.0,
         }
     }
 
-    pub fn set_sr(&mut self, sr: f64) { self.sr = sr; }
+    /// Returns the real and imaginary parts of the analytic signal, the imaginary
+    /// part lags behind the real part by 90 degrees.
+    pub fn step(&mut self, input: f64) -> (f64, f64) {
+        // each section is an all-pass in z^-2: (a^2 - z^-2) / (1 - a^2 z^-2)
+        let mut re = input;
+        for (section, a) in self.re_chain.iter_mut().zip(HILBERT_AP_RE.iter()) {
+            let a2 = a * a;
+            re = section.filter(re, [1.0, 0.0, -a2], [a2, 0.0, -1.0]);
+        }
+        let mut im = input;
+        for (section, a) in self.im_chain.iter_mut().zip(HILBERT_AP_IM.iter()) {
+            let a2 = a * a;
+            im = section.filter(im, [1.0, 0.0, -a2], [a2, 0.0, -1.0]);
+        }
 
-    /// Allows to set the Q-factor by giving a resonance parameter between 0 and 1
-    /// resembling the resonance setting of an analog filter.
-    pub fn set_res(&mut self, res: f64) { self.q = r_to_q(res) + 0.01; }
+        // the imaginary chain is delayed by one extra sample
+        let ret = (re, self.im_z1);
+        self.im_z1 = im;
+        ret
+    }
+}
+
+
+// half-length of the HilbertFir kernel, the kernel has 2 * HILBERT_FIR_LEN + 1 taps
+const HILBERT_FIR_LEN: usize = 64;
+
+/// Linear phase FIR Hilbert transformer, produces an analytic signal, i.e. a pair
+/// of outputs with a 90 degree phase difference.
+/// 
+/// Uses a Blackman-windowed ideal Hilbert kernel with 129 taps, the real part is
+/// just the input delayed to match the kernel. Unlike `HilbertAp`, the phase
+/// difference is exactly 90 degrees at all frequencies, and the outputs are
+/// not phase shifted with respect to the input, but the magnitude of the imaginary
+/// part rolls off near DC and nyquist, being within 0.1dB from about 800Hz to
+/// 21kHz at 44100Hz sample rate.
+/// 
+/// # Caveats
+/// Has a latency of 64 samples.
+pub struct HilbertFir {
+    x: [f64; 2 * HILBERT_FIR_LEN + 1],
+    coeffs: [f64; HILBERT_FIR_LEN],
+}
+
+impl HilbertFir {
+    pub fn new() -> Self {
+        // only odd taps are non-zero, store them all for simplicity
+        let mut coeffs = [0.0; HILBERT_FIR_LEN];
+        for (k, c) in coeffs.iter_mut().enumerate() {
+            let n = k + 1;
+            if n % 2 == 1 {
+                let w = n as f64 / (HILBERT_FIR_LEN + 1) as f64;
+                let blackman = 0.42 + 0.5 * (consts::PI * w).cos() 
+                             + 0.08 * (consts::TAU * w).cos();
+                *c = 2.0 / (consts::PI * n as f64) * blackman;
+            }
+        }
+        Self {
+            x: [0.0; 2 * HILBERT_FIR_LEN + 1],
+            coeffs,
+        }
+    }
+
+    /// Latency in samples.
+    pub fn latency(&self) -> usize { HILBERT_FIR_LEN }
+
+    /// Returns the real and imaginary parts of the analytic signal, the imaginary
+    /// part lags behind the real part by 90 degrees.
+    pub fn step(&mut self, input: f64) -> (f64, f64) {
+        self.x.copy_within(0..2 * HILBERT_FIR_LEN, 1);
+        self.x[0] = input;
+
+        // antisymmetric kernel centered on x[HILBERT_FIR_LEN]
+        let mut im = 0.0;
+        for (k, c) in self.coeffs.iter().enumerate().step_by(2) {
+            im += c * (self.x[HILBERT_FIR_LEN + k + 1] - self.x[HILBERT_FIR_LEN - k - 1]);
+        }
+        (self.x[HILBERT_FIR_LEN], im)
+    }
 }
 
 
@@ -0,0 +1,371 @@
+//! Partitioned FFT convolution, for convolution reverbs, cabinet simulation and
+//! any other long FIR filter.
+//!
+//! The impulse response is split into partitions, which are convolved in the
+//! frequency domain with the overlap-save method, and a frequency-domain delay
+//! line. Two partitioning schemes are available:
+//! - `Partitioning::Uniform`: all partitions have the same size, latency is
+//!   equal to the block size. Cheapest on average, but has latency.
+//! - `Partitioning::NonUniform`: the head of the impulse response is convolved
+//!   directly in the time domain, the rest of it is split into partitions that
+//!   grow by a factor of 4, which is done so that each stage hides its own latency.
+//!   Zero latency, but CPU usage is spiky, as larger blocks are computed all
+//!   at once.
+//!
+//! # Caveats
+//! Since processes are stepped one sample at a time, the FFT of a whole block
+//! is computed in the step that completes that block. This means that the CPU
+//! time of individual steps is not uniform, so make sure to measure performance
+//! over whole buffers.
+
+use std::sync::Arc;
+
+use num::complex::Complex;
+use rustfft::{Fft, FftPlanner};
+
+use crate::traits::Process;
+
+// upper bound for the size of partitions in non-uniform partitioning, larger
+// blocks are more efficient, but make the CPU spikes worse.
+const MAX_BLOCK: usize = 8192;
+
+/// Selects how the impulse response is split into partitions.
+///
+/// - `Uniform(block)`: every partition is `block` samples long, introduces
+///   `block` samples of latency.
+/// - `NonUniform(head)`: the first `head` samples are convolved directly, then
+///   partitions start at `head` samples and grow by a factor of 4 up to 8192
+///   samples. Introduces no latency.
+///
+/// Block sizes are rounded up to the next power of two.
+#[derive(Clone, Copy)]
+pub enum Partitioning {
+    Uniform(usize),
+    NonUniform(usize),
+}
+
+
+// Uniformly partitioned overlap-save convolution of a segment of an impulse
+// response. Has a latency of exactly `block` samples.
+struct ConvStage {
+    block: usize,
+
+    // time domain input, the first half is the previous block, the second
+    // half is filled as new samples come in.
+    in_buf: Vec<f64>,
+
+    // output of the last block, read back one sample at a time
+    out_buf: Vec<f64>,
+
+    // position within the current block
+    pos: usize,
+
+    // spectra of the partitions of the impulse response
+    ir_parts: Vec<Vec<Complex<f64>>>,
+
+    // frequency-domain delay line, holds the spectra of the last input blocks
+    fdl: Vec<Vec<Complex<f64>>>,
+    fdl_ptr: usize,
+
+    fft_buf: Vec<Complex<f64>>,
+    acc_buf: Vec<Complex<f64>>,
+    scratch: Vec<Complex<f64>>,
+    fft_fwd: Arc<dyn Fft<f64>>,
+    fft_bwd: Arc<dyn Fft<f64>>,
+}
+
+impl ConvStage {
+    // `ir` is the segment of the impulse response handled by this stage, it
+    // is zero-padded to a whole number of blocks.
+    fn new(ir: &[f64], block: usize, planner: &mut FftPlanner<f64>) -> Self {
+        let size = 2 * block;
+        let fft_fwd = planner.plan_fft_forward(size);
+        let fft_bwd = planner.plan_fft_inverse(size);
+        let scratch_len = fft_fwd.get_inplace_scratch_len()
+            .max(fft_bwd.get_inplace_scratch_len());
+        let mut scratch = vec![Complex::new(0.0, 0.0); scratch_len];
+
+        // normalization of the inverse FFT is folded into the partitions
+        let norm = 1.0 / size as f64;
+        let num_parts = ir.len().div_ceil(block).max(1);
+        let ir_parts = (0..num_parts).map(|p| {
+            let mut part = vec![Complex::new(0.0, 0.0); size];
+            for (dst, src) in part.iter_mut().zip(ir.iter().skip(p * block).take(block)) {
+                *dst = Complex::new(*src * norm, 0.0);
+            }
+            fft_fwd.process_with_scratch(&mut part, &mut scratch);
+            part
+        }).collect();
+
+        Self {
+            block,
+            in_buf: vec![0.0; size],
+            out_buf: vec![0.0; block],
+            pos: 0,
+            ir_parts,
+            fdl: vec![vec![Complex::new(0.0, 0.0); size]; num_parts],
+            fdl_ptr: 0,
+            fft_buf: vec![Complex::new(0.0, 0.0); size],
+            acc_buf: vec![Complex::new(0.0, 0.0); size],
+            scratch,
+            fft_fwd,
+            fft_bwd,
+        }
+    }
+
+    fn step(&mut self, input: f64) -> f64 {
+        // read before writing, this makes latency exactly one block
+        let ret = self.out_buf[self.pos];
+        self.in_buf[self.block + self.pos] = input;
+        self.pos += 1;
+        if self.pos == self.block {
+            self.pos = 0;
+            self.process_block();
+        }
+        ret
+    }
+
+    fn process_block(&mut self) {
+        let size = 2 * self.block;
+        let num_parts = self.ir_parts.len();
+
+        // forward FFT of the last two blocks, goes into the delay line
+        self.fdl_ptr = (self.fdl_ptr + num_parts - 1) % num_parts;
+        let spectrum = &mut self.fdl[self.fdl_ptr];
+        for (dst, src) in spectrum.iter_mut().zip(self.in_buf.iter()) {
+            *dst = Complex::new(*src, 0.0);
+        }
+        self.fft_fwd.process_with_scratch(spectrum, &mut self.scratch);
+
+        // multiply-accumulate every partition with the matching input spectrum
+        for bin in self.acc_buf.iter_mut() { *bin = Complex::new(0.0, 0.0); }
+        for (p, part) in self.ir_parts.iter().enumerate() {
+            let spectrum = &self.fdl[(self.fdl_ptr + p) % num_parts];
+            for ((acc, x), h) in self.acc_buf.iter_mut().zip(spectrum.iter()).zip(part.iter()) {
+                *acc += x * h;
+            }
+        }
+
+        // inverse FFT, only the second half is valid in overlap-save
+        self.fft_buf.copy_from_slice(&self.acc_buf);
+        self.fft_bwd.process_with_scratch(&mut self.fft_buf, &mut self.scratch);
+        for (dst, src) in self.out_buf.iter_mut().zip(self.fft_buf[self.block..size].iter()) {
+            *dst = src.re;
+        }
+
+        // slide input window
+        self.in_buf.copy_within(self.block..size, 0);
+    }
+}
+
+
+/// Mono partitioned FFT convolution.
+///
+/// # Examples
+/// ```
+/// use dsp_lab::core::lin_filter::convolution::{Convolver, Partitioning};
+/// use dsp_lab::traits::Process;
+/// let mut conv = Convolver::new();
+/// conv.set_partitioning(Partitioning::NonUniform(64));
+/// conv.load_ir(&[0.5, 0.25, 0.125]);
+/// assert_eq!(conv.latency(), 0);
+/// assert_eq!(conv.step(1.0), 0.5);
+/// ```
+pub struct Convolver {
+    partitioning: Partitioning,
+
+    // directly convolved head of the impulse response, and the matching
+    // input history stored twice in a row, so that it can be read linearly
+    head: Vec<f64>,
+    head_hist: Vec<f64>,
+    head_ptr: usize,
+
+    stages: Vec<ConvStage>,
+    latency: usize,
+}
+
+impl Convolver {
+    /// Creates an empty convolver, which outputs silence until an impulse response
+    /// is loaded. Defaults to zero latency non-uniform partitioning, with a head
+    /// of 64 samples.
+    pub fn new() -> Self {
+        Self {
+            partitioning: Partitioning::NonUniform(64),
+            head: Vec::new(),
+            head_hist: Vec::new(),
+            head_ptr: 0,
+            stages: Vec::new(),
+            latency: 0,
+        }
+    }
+
+    /// Changes the partitioning scheme. Only takes effect after the next call
+    /// to `load_ir()`.
+    pub fn set_partitioning(&mut self, partitioning: Partitioning) {
+        self.partitioning = partitioning;
+    }
+
+    /// Latency in samples introduced by the current partitioning.
+    pub fn latency(&self) -> usize { self.latency }
+
+    /// Loads a new impulse response and clears the internal state. This allocates
+    /// and plans the FFTs, so it should not be called from the audio thread.
+    pub fn load_ir(&mut self, ir: &[f64]) {
+        let mut planner = FftPlanner::new();
+        self.stages.clear();
+        self.head.clear();
+        self.head_ptr = 0;
+
+        match self.partitioning {
+            Partitioning::Uniform(block) => {
+                let block = block.max(1).next_power_of_two();
+                self.latency = block;
+                if !ir.is_empty() {
+                    self.stages.push(ConvStage::new(ir, block, &mut planner));
+                }
+            },
+            Partitioning::NonUniform(head) => {
+                let head = head.max(1).next_power_of_two();
+                self.latency = 0;
+                self.head.extend(ir.iter().take(head));
+
+                // each stage starts at an offset equal to its block size, so its
+                // latency is exactly compensated. With 3 partitions per stage and
+                // a growth factor of 4, the next stage always starts at 4 times
+                // the current block size.
+                let mut start = head;
+                let mut block = head;
+                while start < ir.len() {
+                    let end = if block >= MAX_BLOCK {
+                        ir.len()
+                    } else {
+                        (start + 3 * block).min(ir.len())
+                    };
+                    self.stages.push(ConvStage::new(&ir[start..end], block, &mut planner));
+                    start = end;
+                    block = (block * 4).min(MAX_BLOCK);
+                }
+            },
+        }
+
+        self.head_hist = vec![0.0; 2 * self.head.len()];
+    }
+}
+
+impl Process<f64> for Convolver {
+    fn step(&mut self, input: f64) -> f64 {
+        let len = self.head.len();
+        let mut accum = 0.0;
+
+        // direct convolution of the head
+        if len > 0 {
+            self.head_ptr = (self.head_ptr + len - 1) % len;
+            self.head_hist[self.head_ptr] = input;
+            self.head_hist[self.head_ptr + len] = input;
+            let hist = &self.head_hist[self.head_ptr..self.head_ptr + len];
+            for (h, x) in self.head.iter().zip(hist.iter()) {
+                accum += h * x;
+            }
+        }
+
+        // partitioned tail
+        for stage in self.stages.iter_mut() {
+            accum += stage.step(input);
+        }
+        accum
+    }
+}
+
+
+/// Stereo partitioned FFT convolution, each channel is convolved with its own
+/// impulse response, with no cross-talk.
+pub struct StereoConvolver {
+    left: Convolver,
+    right: Convolver,
+}
+
+impl StereoConvolver {
+    pub fn new() -> Self {
+        Self {
+            left: Convolver::new(),
+            right: Convolver::new(),
+        }
+    }
+
+    /// Changes the partitioning scheme. Only takes effect after the next call
+    /// to `load_ir()`.
+    pub fn set_partitioning(&mut self, partitioning: Partitioning) {
+        self.left.set_partitioning(partitioning);
+        self.right.set_partitioning(partitioning);
+    }
+
+    /// Latency in samples introduced by the current partitioning.
+    pub fn latency(&self) -> usize { self.left.latency() }
+
+    /// Loads a stereo impulse response, as two separate channels.
+    pub fn load_ir(&mut self, left: &[f64], right: &[f64]) {
+        self.left.load_ir(left);
+        self.right.load_ir(right);
+    }
+
+    // TODO: implement Process once a stereo pair type is available, like in
+    // StereoFirDiffuser.
+    pub fn step(&mut self, input: (f64, f64)) -> (f64, f64) {
+        (self.left.step(input.0), self.right.step(input.1))
+    }
+}
+
+
+/// True-stereo partitioned FFT convolution, with 4-channel impulse responses,
+/// as recorded in real spaces by exciting them from a left and a right source.
+///
+/// Each input channel is convolved with an impulse response for each output
+/// channel, so that the stereo image of the input is preserved and smeared as
+/// it would be in the real space.
+pub struct TrueStereoConvolver {
+    l_to_l: Convolver,
+    l_to_r: Convolver,
+    r_to_l: Convolver,
+    r_to_r: Convolver,
+}
+
+impl TrueStereoConvolver {
+    pub fn new() -> Self {
+        Self {
+            l_to_l: Convolver::new(),
+            l_to_r: Convolver::new(),
+            r_to_l: Convolver::new(),
+            r_to_r: Convolver::new(),
+        }
+    }
+
+    /// Changes the partitioning scheme. Only takes effect after the next call
+    /// to `load_ir()`.
+    pub fn set_partitioning(&mut self, partitioning: Partitioning) {
+        self.l_to_l.set_partitioning(partitioning);
+        self.l_to_r.set_partitioning(partitioning);
+        self.r_to_l.set_partitioning(partitioning);
+        self.r_to_r.set_partitioning(partitioning);
+    }
+
+    /// Latency in samples introduced by the current partitioning.
+    pub fn latency(&self) -> usize { self.l_to_l.latency() }
+
+    /// Loads a true-stereo impulse response, as four separate channels. The
+    /// channels are named as `<input>_to_<output>`.
+    pub fn load_ir(&mut self, l_to_l: &[f64], l_to_r: &[f64], r_to_l: &[f64], r_to_r: &[f64]) {
+        self.l_to_l.load_ir(l_to_l);
+        self.l_to_r.load_ir(l_to_r);
+        self.r_to_l.load_ir(r_to_l);
+        self.r_to_r.load_ir(r_to_r);
+    }
+
+    // TODO: implement Process once a stereo pair type is available, like in
+    // StereoFirDiffuser.
+    pub fn step(&mut self, input: (f64, f64)) -> (f64, f64) {
+        let (left, right) = input;
+        let ret_l = self.l_to_l.step(left) + self.r_to_l.step(right);
+        let ret_r = self.l_to_r.step(left) + self.r_to_r.step(right);
+        (ret_l, ret_r)
+    }
+}
@@ -5,11 +5,17 @@
 pub mod lin_filter;            // linear filters
 pub mod non_lin_filters;       // non-linear filters, like slew limiters, rolling median
 pub mod osc;
-// pub mod envelopes;           TODO:
+pub mod wavetable;              // mip-mapped wavetable oscillator
+pub mod fm;                     // phase modulation operators and voices
+pub mod additive;               // additive oscillator bank
+pub mod envelopes;              // envelope generators
 pub mod chaos;                  // random and noise
 pub mod delay;               // TODO: delay line with interpolation
 //pub mod dft;               // DFT algorithms
 pub mod reverb;                 // reverb primitives
+pub mod waveshapers;            // waveshapers, with and without anti-aliasing
+pub mod oversampling;           // polyphase up and down sampling
+pub mod resampling;             // arbitrary ratio sample rate conversion
 
 use crate::traits::{Process, Source};
 // use crate::core::chaos::RandomToggle;        TODO: uncomment when ready
@@ -1,31 +1,99 @@
 use crate::traits::Process;
-use crate::core::lin_filter::{Diff, LeakyInt};
-use crate::utils::math::var_clip;
+use crate::core::lin_filter::{DiffFwd, IntegLeaky};
+use crate::utils::math::{var_clip, fast_sigmoid, pre_post_gains};
 
+use std::f64::consts;
+use std::collections::VecDeque;
+
+// === SLEW LIMITERS ===
+
+/// Slew rate limiter, the output follows the input, but never moves faster than
+/// `rise` units per second upwards and `fall` units per second downwards.
+/// 
+/// Since the rates are in units per second, it behaves the same at any sample
+/// rate. Turns steps into linear ramps, useful for portamento, de-clicking
+/// parameter changes, or as an asymmetric envelope follower.
+pub struct SlewLimiter {
+    y_z1: f64,
+    inv_sr: f64,
+    pub rise: f64,
+    pub fall: f64,
+}
+
+impl SlewLimiter {
+    pub fn new() -> Self {
+        Self {
+            y_z1: 0.0,
+            inv_sr: 1.0 / 44100.0,
+            rise: 1000.0,
+            fall: 1000.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        self.inv_sr = 1.0 / sr;
+    }
+}
+
+impl Process<f64> for SlewLimiter {
+    fn step(&mut self, input: f64) -> f64 {
+        let max_up   =  self.rise.abs() * self.inv_sr;
+        let max_down = -self.fall.abs() * self.inv_sr;
+        self.y_z1 += (input - self.y_z1).clamp(max_down, max_up);
+        self.y_z1
+    }
+}
+
+
+// Leak time constants of the slew clippers, in milliseconds. They match the
+// leaks of 0.02 and 0.08 per sample at 44.1kHz, which the slew clippers were
+// originally tuned with.
+const SLEW_CLIP_1_LEAK: f64 = 1.1224108039117713;
+const SLEW_CLIP_2_LEAK: f64 = 0.2719513001725165;
+
+// Gain correction for a leaky integrator with a leak of `leak_ms`, relative to
+// 44.1kHz. The leaky integrator's gain is off by roughly `1 + 1 / (2 * tau)`,
+// with `tau` the leak in samples, which is significant for the very short leaks
+// of the slew clippers, so it is normalized to what it is at 44.1kHz.
+fn leak_comp(leak_ms: f64, sr: f64) -> f64 {
+    let gain = |sr: f64| {
+        let tau = leak_ms * 0.001 * sr;
+        (1.0 - (-1.0 / tau).exp()) * tau
+    };
+    gain(sr) / gain(44100.0)
+}
+
+/// Slew clipper, saturates the first derivative of the signal, softly limiting
+/// how fast it can change. Sounds like a low-pass that only kicks in on loud
+/// transients.
+/// 
+/// The derivative is computed in units per 44.1kHz sample, so `drive` and
+/// `hardness` sound the same at any sample rate.
 pub struct SlewClip1 {
-    diff: Diff,
-    int: LeakyInt,
-    sr_scale: f64,
-    dt_scale: f64,
+    diff: DiffFwd,
+    int: IntegLeaky,
+    comp: f64,
     pub hardness: f64,
     pub drive: f64,
 }
 
 impl SlewClip1 {
     pub fn new() -> Self {
-        Self {
-            diff: Diff::new(),
-            int:  LeakyInt::new(0.02, 0.0),
-            sr_scale: 1.0,
-            dt_scale: 1.0,
+        let mut ret = Self {
+            diff: DiffFwd::new(),
+            int:  IntegLeaky::new(),
+            comp: 1.0,
             hardness: 0.5,
             drive: 0.0,
-        }
+        };
+        ret.int.set_leak(SLEW_CLIP_1_LEAK);
+        ret
     }
 
     pub fn set_sr(&mut self, sr: f64) {
-        self.sr_scale = sr / 44100.0;
-        self.dt_scale = 44100.0 / sr.clamp(1.0, std::f64::MAX);
+        self.diff.set_sr(sr);
+        self.int.set_sr(sr);
+        self.comp = leak_comp(SLEW_CLIP_1_LEAK, sr);
     }
 }
 
@@ -34,41 +102,51 @@ impl Process<f64> for SlewClip1 {
         let post_gain = 1.0 - self.drive;
         let pre_gain  = 1.0 / post_gain.clamp(1e-30, 1.0);
 
-        let dx = self.diff.step(input) * self.sr_scale;
+        let dx = self.diff.step(input);
         let dx_sat = var_clip(dx * pre_gain, self.hardness);
-        self.int.step(dx_sat * post_gain) * self.dt_scale
+        self.int.step(dx_sat * post_gain * self.comp)
     }
 }
 
 
+/// Second order slew clipper, saturates the second derivative of the signal,
+/// softly limiting how fast the slope can change. Rounds off corners rather
+/// than slopes, for a softer sound than `SlewClip1`.
+/// 
+/// The derivatives are computed in units per 44.1kHz sample, so `drive` and
+/// `hardness` sound the same at any sample rate.
 pub struct SlewClip2 {
-    diff1: Diff,
-    diff2: Diff,
-    int1: LeakyInt,
-    int2: LeakyInt,
-    sr_scale: f64,
-    dt_scale: f64,
+    diff1: DiffFwd,
+    diff2: DiffFwd,
+    int1: IntegLeaky,
+    int2: IntegLeaky,
+    comp: f64,
     pub hardness: f64,
     pub drive: f64,
 }
 
 impl SlewClip2 {
     pub fn new() -> Self {
-        Self {
-            diff1: Diff::new(),
-            diff2: Diff::new(),
-            int1:  LeakyInt::new(0.08, 0.0),
-            int2:  LeakyInt::new(0.08, 0.0),
-            sr_scale: 1.0,
-            dt_scale: 1.0,
+        let mut ret = Self {
+            diff1: DiffFwd::new(),
+            diff2: DiffFwd::new(),
+            int1:  IntegLeaky::new(),
+            int2:  IntegLeaky::new(),
+            comp: 1.0,
             hardness: 0.5,
             drive: 0.0,
-        }
+        };
+        ret.int1.set_leak(SLEW_CLIP_2_LEAK);
+        ret.int2.set_leak(SLEW_CLIP_2_LEAK);
+        ret
     }
 
     pub fn set_sr(&mut self, sr: f64) {
-        self.sr_scale = sr / 44100.0;
-        self.dt_scale = 44100.0 / sr.clamp(1.0, std::f64::MAX);
+        self.diff1.set_sr(sr);
+        self.diff2.set_sr(sr);
+        self.int1.set_sr(sr);
+        self.int2.set_sr(sr);
+        self.comp = leak_comp(SLEW_CLIP_2_LEAK, sr);
     }
 }
 
@@ -77,11 +155,665 @@ impl Process<f64> for SlewClip2 {
         let post_gain = 1.0 - self.drive;
         let pre_gain  = 1.0 / post_gain.clamp(1e-30, 1.0);
 
-        let dx1 = self.diff1.step(input) * self.sr_scale;
-        let dx2 = self.diff2.step(dx1)   * self.sr_scale;
+        let dx1 = self.diff1.step(input);
+        let dx2 = self.diff2.step(dx1);
         let dx_sat = var_clip(dx2 * pre_gain, self.hardness);
-        let y = self.int1.step(dx_sat * post_gain) * self.dt_scale;
-        self.int2.step(y) * self.dt_scale
+        let y = self.int1.step(dx_sat * post_gain * self.comp);
+        self.int2.step(y * self.comp)
+    }
+}
+
+
+
+// === NON-LINEAR INTEGRATORS ===
+// These are meant as building blocks for virtual analog filters, where each
+// integrator models a capacitor being charged by some non-ideal active component.
+
+/// Clipping integrator, models an op-amp integrator hitting the supply rails.
+/// 
+/// Integrates like `Integ`, but the state is hard-clipped to `[-limit, limit]`.
+/// When the input changes direction, the integrator immediately leaves the rail.
+pub struct IntegClip {
+    y_z1: f64,
+    inv_sr_scale: f64,
+    pub limit: f64,
+}
+
+impl IntegClip {
+    pub fn new() -> Self {
+        Self {
+            y_z1: 0.0,
+            inv_sr_scale: 1.0,
+            limit: 1.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        self.inv_sr_scale = 44100.0 / sr;
+    }
+}
+
+impl Process<f64> for IntegClip {
+    fn step(&mut self, input: f64) -> f64 {
+        let limit = self.limit.abs();
+        self.y_z1 = (self.y_z1 + self.inv_sr_scale * input).clamp(-limit, limit);
+        self.y_z1
+    }
+}
+
+
+/// Saturating integrator, the state softly approaches `[-limit, limit]`.
+/// 
+/// The closer the state gets to the limit, the slower it moves outwards, as the
+/// rate of change is scaled by `1 - (y / limit)^2`. Movement towards zero is not
+/// slowed down, so unlike a saturator placed after an integrator, there is
+/// no "sticking" to the rails, and unlike a saturator inside the feedback
+/// path, there is no leak.
+pub struct IntegSat {
+    y_z1: f64,
+    inv_sr_scale: f64,
+    pub limit: f64,
+}
+
+impl IntegSat {
+    pub fn new() -> Self {
+        Self {
+            y_z1: 0.0,
+            inv_sr_scale: 1.0,
+            limit: 1.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        self.inv_sr_scale = 44100.0 / sr;
+    }
+}
+
+impl Process<f64> for IntegSat {
+    fn step(&mut self, input: f64) -> f64 {
+        let limit = self.limit.abs().max(1e-30);
+        let mut dy = self.inv_sr_scale * input;
+        if dy * self.y_z1 > 0.0 {
+            let r = (self.y_z1 / limit).clamp(-1.0, 1.0);
+            dy *= 1.0 - r * r;
+        }
+
+        // large steps can still overshoot, so clamp as a last resort
+        self.y_z1 = (self.y_z1 + dy).clamp(-limit, limit);
+        self.y_z1
+    }
+}
+
+
+/// OTA integrator, models an operational transconductance amplifier charging
+/// a capacitor, which is the basic building block of most analog synth filters.
+/// 
+/// The input is saturated with `tanh()` before integration, so the integrator
+/// can only slew at a limited rate. `level` sets the input level at which the
+/// saturation kicks in, lower values mean more distortion.
+/// 
+/// # Caveats
+/// * Will overflow with DC signals, like `Integ`, when used in a filter the
+///   feedback will usually keep it in check.
+pub struct IntegOTA {
+    y_z1: f64,
+    inv_sr_scale: f64,
+    pub level: f64,
+}
+
+impl IntegOTA {
+    pub fn new() -> Self {
+        Self {
+            y_z1: 0.0,
+            inv_sr_scale: 1.0,
+            level: 1.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        self.inv_sr_scale = 44100.0 / sr;
+    }
+}
+
+impl Process<f64> for IntegOTA {
+    fn step(&mut self, input: f64) -> f64 {
+        let level = self.level.abs().max(1e-30);
+        self.y_z1 += self.inv_sr_scale * level * (input / level).tanh();
+        self.y_z1
+    }
+}
+
+
+
+// === ZERO-DELAY FEEDBACK VIRTUAL ANALOG FILTERS ===
+
+// Generic N-pole zero-delay feedback core, used internally by the ladder and 
+// Sallen-Key filters. Simulates the linear system dy/dt = A y + b u, with
+// trapezoidal integration (TPT), where the input u = sat(x - k * c.y) depends on
+// the output through a saturating feedback path. The implicit equation for the 
+// feedback signal is solved with Newton's method every sample.
+// The cutoff frequency is normalized, so A describes the filter at 1 rad/s.
+struct ZdfCore<const N: usize> {
+    a: [[f64; N]; N],
+    b: [f64; N],
+    c: [f64; N],
+
+    // states of the trapezoidal integrators, and outputs of each stage
+    s: [f64; N],
+    pub y: [f64; N],
+
+    // LU decomposition of (I - gA) and solution of (I - gA) q = b, these are
+    // only recomputed when the cutoff changes.
+    lu: [[f64; N]; N],
+    q: [f64; N],
+    g: f64,
+
+    // last value of the feedback signal, used as initial guess
+    z: f64,
+}
+
+impl<const N: usize> ZdfCore<N> {
+    fn new(a: [[f64; N]; N], b: [f64; N], c: [f64; N]) -> Self {
+        let mut ret = Self {
+            a, b, c,
+            s: [0.0; N],
+            y: [0.0; N],
+            lu: [[0.0; N]; N],
+            q: [0.0; N],
+            g: -1.0,
+            z: 0.0,
+        };
+        ret.set_g(0.0);
+        ret
+    }
+
+    // Update the integrator gain, for a cutoff of 1 rad/s this is tan(omega / 2)
+    fn set_g(&mut self, g: f64) {
+        if g == self.g { return; }
+        self.g = g;
+
+        // (I - gA) is diagonally dominant for all the filters in this module,
+        // so no pivoting is needed.
+        for i in 0..N {
+            for j in 0..N {
+                self.lu[i][j] = if i == j { 1.0 } else { 0.0 } - g * self.a[i][j];
+            }
+        }
+        for i in 0..N {
+            for r in (i + 1)..N {
+                let f = self.lu[r][i] / self.lu[i][i];
+                self.lu[r][i] = f;
+                for c in (i + 1)..N {
+                    self.lu[r][c] -= f * self.lu[i][c];
+                }
+            }
+        }
+        self.q = self.solve(self.b);
+    }
+
+    fn solve(&self, mut x: [f64; N]) -> [f64; N] {
+        for i in 0..N {
+            for j in 0..i {
+                x[i] -= self.lu[i][j] * x[j];
+            }
+        }
+        for i in (0..N).rev() {
+            for j in (i + 1)..N {
+                x[i] -= self.lu[i][j] * x[j];
+            }
+            x[i] /= self.lu[i][i];
+        }
+        x
+    }
+
+    fn filter(&mut self, x: f64, k: f64) {
+        // the output is y = p + g q u, so the feedback signal is z = z_0 + g_z u
+        let p = self.solve(self.s);
+        let mut z_0 = 0.0;
+        let mut g_z = 0.0;
+        for ((c, p), q) in self.c.iter().zip(&p).zip(&self.q) {
+            z_0 += c * p;
+            g_z += c * q;
+        }
+        g_z *= self.g;
+
+        // Newton's method for z - z_0 - g_z sat(x - k z) = 0, the derivative of 
+        // fast_sigmoid is (1 - fast_sigmoid^2)^(3/2)
+        let mut z = self.z;
+        for _ in 0..8 {
+            let sat = fast_sigmoid(x - k * z);
+            let d_sat = (1.0 - sat * sat).powf(1.5);
+            let f = z - z_0 - g_z * sat;
+            let mut df = 1.0 + g_z * k * d_sat;
+            if df.abs() < 1e-9 { df = 1e-9_f64.copysign(df); }
+            let dz = f / df;
+            z -= dz;
+            if dz.abs() < 1e-10 { break; }
+        }
+        if !z.is_finite() { z = 0.0; }
+        self.z = z;
+
+        // update outputs and states
+        let u = fast_sigmoid(x - k * z);
+        let states = self.y.iter_mut().zip(self.s.iter_mut());
+        for ((y, s), (p, q)) in states.zip(p.iter().zip(&self.q)) {
+            *y = p + self.g * q * u;
+            *s = 2.0 * *y - *s;
+        }
+    }
+}
+
+
+/// 4-pole transistor ladder low-pass, in the style of the Moog ladder filter.
+/// 
+/// Four identical 1-pole stages in series, with negative feedback from the 
+/// last stage, through a saturating input stage. 24dB/oct slope.
+/// 
+/// - `cutoff`: cutoff frequency in hertz, which is also the frequency of the
+///   resonant peak.
+/// - `res`: resonance, self-oscillates above 1.0.
+/// - `drive`: positive values drive the saturation harder, with matching output
+///   gain compensation, negative values make it cleaner.
+/// 
+/// # Caveats
+/// * As in the original circuit, the passband gain drops as resonance increases.
+/// * Self-oscillation needs some input to get started, a filter that has only
+///   ever been fed silence will stay silent.
+pub struct LadderLowPass {
+    core: ZdfCore<4>,
+    sr: f64,
+    pub cutoff: f64,
+    pub res: f64,
+    pub drive: f64,
+}
+
+impl LadderLowPass {
+    pub fn new() -> Self {
+        Self {
+            core: ZdfCore::new(
+                [[-1.0,  0.0,  0.0,  0.0],
+                 [ 1.0, -1.0,  0.0,  0.0],
+                 [ 0.0,  1.0, -1.0,  0.0],
+                 [ 0.0,  0.0,  1.0, -1.0]],
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0]),
+            sr: 44100.0,
+            cutoff: 1000.0,
+            res: 0.0,
+            drive: 0.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) { self.sr = sr; }
+}
+
+impl Process<f64> for LadderLowPass {
+    fn step(&mut self, input: f64) -> f64 {
+        let f = self.cutoff.clamp(0.0, self.sr * 0.49);
+        self.core.set_g((consts::PI * f / self.sr).tan());
+        let (pre, post) = pre_post_gains(self.drive);
+        self.core.filter(input * pre, 4.0 * self.res);
+        self.core.y[3] * post
+    }
+}
+
+
+// normalized frequency of the resonant peak of the diode ladder, sqrt(10 / 7)
+const DIODE_PEAK: f64 = 1.1952286093343936;
+
+// feedback gain at which the diode ladder starts self-oscillating
+const DIODE_K: f64 = 901.0 / 49.0;
+
+/// 4-pole diode ladder low-pass, in the style of the EMS VCS3 and TB-303 filters.
+/// 
+/// Unlike the transistor ladder, the stages are not buffered, so each stage
+/// loads the previous one, which makes the slope gentler and the resonance
+/// more squelchy. Uses the same parameters as `LadderLowPass`.
+/// 
+/// # Caveats
+/// * The passband gain drops as resonance increases, more so than in the
+///   transistor ladder.
+/// * Self-oscillation needs some input to get started, a filter that has only
+///   ever been fed silence will stay silent.
+pub struct DiodeLadderLowPass {
+    core: ZdfCore<4>,
+    sr: f64,
+    pub cutoff: f64,
+    pub res: f64,
+    pub drive: f64,
+}
+
+impl DiodeLadderLowPass {
+    pub fn new() -> Self {
+        Self {
+            core: ZdfCore::new(
+                [[-2.0,  1.0,  0.0,  0.0],
+                 [ 1.0, -2.0,  1.0,  0.0],
+                 [ 0.0,  1.0, -2.0,  1.0],
+                 [ 0.0,  0.0,  1.0, -1.0]],
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0]),
+            sr: 44100.0,
+            cutoff: 1000.0,
+            res: 0.0,
+            drive: 0.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) { self.sr = sr; }
+}
+
+impl Process<f64> for DiodeLadderLowPass {
+    fn step(&mut self, input: f64) -> f64 {
+        // prewarp so that the resonant peak lands exactly on the cutoff
+        let f = self.cutoff.clamp(0.0, self.sr * 0.49);
+        self.core.set_g((consts::PI * f / self.sr).tan() / DIODE_PEAK);
+        let (pre, post) = pre_post_gains(self.drive);
+        self.core.filter(input * pre, DIODE_K * self.res);
+        self.core.y[3] * post
+    }
+}
+
+
+/// 2-pole Sallen-Key low-pass, in the style of the Korg MS-20 filter.
+/// 
+/// Two 1-pole stages with positive feedback of the difference between them,
+/// through a saturating stage, which gives the characteristic screaming 
+/// resonance. 12dB/oct slope. Uses the same parameters as `LadderLowPass`.
+/// 
+/// # Caveats
+/// * Self-oscillation needs some input to get started, a filter that has only
+///   ever been fed silence will stay silent.
+pub struct SallenKeyLowPass {
+    core: ZdfCore<2>,
+    sr: f64,
+    pub cutoff: f64,
+    pub res: f64,
+    pub drive: f64,
+}
+
+impl SallenKeyLowPass {
+    pub fn new() -> Self {
+        Self {
+            core: ZdfCore::new(
+                [[-1.0,  0.0],
+                 [ 1.0, -1.0]],
+                [1.0, 0.0],
+                [-1.0, 1.0]),
+            sr: 44100.0,
+            cutoff: 1000.0,
+            res: 0.0,
+            drive: 0.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) { self.sr = sr; }
+}
+
+impl Process<f64> for SallenKeyLowPass {
+    fn step(&mut self, input: f64) -> f64 {
+        let f = self.cutoff.clamp(0.0, self.sr * 0.49);
+        self.core.set_g((consts::PI * f / self.sr).tan());
+        let (pre, post) = pre_post_gains(self.drive);
+        self.core.filter(input * pre, 2.0 * self.res);
+        self.core.y[1] * post
     }
 }
 
+
+// === ROLLING STATISTICS ===
+// Statistics over a sliding window of the last `window` samples. Window lengths
+// can be changed at run-time, when the window grows, the statistics are computed
+// over the samples available until the window fills up again.
+
+/// Rolling median, great for removing impulse noise (clicks, crackles, spikes)
+/// while preserving edges, unlike a low-pass.
+/// 
+/// Keeps a sorted copy of the window, so each step costs a binary search and a
+/// memory move the size of the window, which is very fast for the short windows
+/// (3 to a few hundred samples) used for impulse noise removal.
+pub struct RollingMedian {
+    history: VecDeque<f64>,
+    sorted: Vec<f64>,
+    window: usize,
+}
+
+impl RollingMedian {
+    /// Creates a rolling median over 5 samples.
+    pub fn new() -> Self {
+        Self {
+            history: VecDeque::new(),
+            sorted: Vec::new(),
+            window: 5,
+        }
+    }
+
+    /// Sets the window length in samples, at least 1. This is a method and not
+    /// a field, because shrinking the window discards the oldest samples.
+    pub fn set_window(&mut self, window: usize) {
+        self.window = window.max(1);
+        while self.history.len() > self.window { self.pop_oldest(); }
+    }
+
+    pub fn window(&self) -> usize { self.window }
+
+    fn pop_oldest(&mut self) {
+        if let Some(old) = self.history.pop_front() {
+            let idx = self.sorted.partition_point(|v| *v < old);
+            self.sorted.remove(idx);
+        }
+    }
+}
+
+impl Process<f64> for RollingMedian {
+    fn step(&mut self, input: f64) -> f64 {
+        // NaN would break the ordering of the window
+        let input = if input.is_nan() { 0.0 } else { input };
+        if self.history.len() >= self.window { self.pop_oldest(); }
+        self.history.push_back(input);
+        let idx = self.sorted.partition_point(|v| *v < input);
+        self.sorted.insert(idx, input);
+
+        let n = self.sorted.len();
+        if n % 2 == 1 {
+            self.sorted[n / 2]
+        } else {
+            0.5 * (self.sorted[n / 2 - 1] + self.sorted[n / 2])
+        }
+    }
+}
+
+
+// monotonic deque, keeps the candidates for the extreme of the window, in order
+// of arrival, so that the front is always the extreme. `is_max` selects wether
+// the extreme is the maximum or the minimum.
+struct MonotonicDeque {
+    deque: VecDeque<(u64, f64)>,
+    count: u64,
+    window: usize,
+    is_max: bool,
+}
+
+impl MonotonicDeque {
+    fn new(is_max: bool) -> Self {
+        Self {
+            deque: VecDeque::new(),
+            count: 0,
+            window: 64,
+            is_max,
+        }
+    }
+
+    fn set_window(&mut self, window: usize) {
+        self.window = window.max(1);
+        self.expire();
+    }
+
+    // drops the candidates that left the window
+    fn expire(&mut self) {
+        while let Some((idx, _)) = self.deque.front() {
+            if self.count - idx >= self.window as u64 { self.deque.pop_front(); }
+            else { break; }
+        }
+    }
+
+    fn step(&mut self, input: f64) -> f64 {
+        let input = if input.is_nan() { 0.0 } else { input };
+        // drop the candidates that can never be the extreme again
+        while let Some((_, v)) = self.deque.back() {
+            let dominated = if self.is_max { *v <= input } else { *v >= input };
+            if dominated { self.deque.pop_back(); } else { break; }
+        }
+        self.count += 1;
+        self.deque.push_back((self.count, input));
+        self.expire();
+        self.deque.front().map_or(input, |(_, v)| *v)
+    }
+}
+
+/// Rolling maximum, in amortized constant time regardless of the window length.
+/// Useful as a peak-hold envelope, or as the dilation operator of morphological
+/// filters.
+pub struct RollingMax { deque: MonotonicDeque }
+
+impl RollingMax {
+    /// Creates a rolling maximum over 64 samples.
+    pub fn new() -> Self { Self { deque: MonotonicDeque::new(true) } }
+
+    /// Sets the window length in samples, at least 1.
+    pub fn set_window(&mut self, window: usize) { self.deque.set_window(window); }
+
+    pub fn window(&self) -> usize { self.deque.window }
+}
+
+impl Process<f64> for RollingMax {
+    fn step(&mut self, input: f64) -> f64 { self.deque.step(input) }
+}
+
+/// Rolling minimum, in amortized constant time regardless of the window length.
+/// Useful as the erosion operator of morphological filters.
+pub struct RollingMin { deque: MonotonicDeque }
+
+impl RollingMin {
+    /// Creates a rolling minimum over 64 samples.
+    pub fn new() -> Self { Self { deque: MonotonicDeque::new(false) } }
+
+    /// Sets the window length in samples, at least 1.
+    pub fn set_window(&mut self, window: usize) { self.deque.set_window(window); }
+
+    pub fn window(&self) -> usize { self.deque.window }
+}
+
+impl Process<f64> for RollingMin {
+    fn step(&mut self, input: f64) -> f64 { self.deque.step(input) }
+}
+
+
+// running sums of the window and of its squares. The sums are recomputed from
+// scratch once per window length, so that rounding errors don't accumulate.
+struct RollingSums {
+    history: VecDeque<f64>,
+    sum: f64,
+    sum_sq: f64,
+    since_refresh: usize,
+    window: usize,
+}
+
+impl RollingSums {
+    fn new() -> Self {
+        Self {
+            history: VecDeque::new(),
+            sum: 0.0,
+            sum_sq: 0.0,
+            since_refresh: 0,
+            window: 64,
+        }
+    }
+
+    fn set_window(&mut self, window: usize) {
+        self.window = window.max(1);
+        while self.history.len() > self.window { self.history.pop_front(); }
+        self.refresh();
+    }
+
+    fn refresh(&mut self) {
+        self.sum = self.history.iter().sum();
+        self.sum_sq = self.history.iter().map(|x| x * x).sum();
+        self.since_refresh = 0;
+    }
+
+    // returns mean and mean of squares
+    fn step(&mut self, input: f64) -> (f64, f64) {
+        if self.history.len() >= self.window {
+            if let Some(old) = self.history.pop_front() {
+                self.sum -= old;
+                self.sum_sq -= old * old;
+            }
+        }
+        self.history.push_back(input);
+        self.sum += input;
+        self.sum_sq += input * input;
+
+        self.since_refresh += 1;
+        if self.since_refresh >= self.window || !self.sum_sq.is_finite() {
+            self.refresh();
+        }
+        let inv_n = 1.0 / self.history.len() as f64;
+        (self.sum * inv_n, self.sum_sq.max(0.0) * inv_n)
+    }
+}
+
+/// Rolling mean, i.e. a moving average (boxcar) filter.
+pub struct RollingMean { sums: RollingSums }
+
+impl RollingMean {
+    /// Creates a rolling mean over 64 samples.
+    pub fn new() -> Self { Self { sums: RollingSums::new() } }
+
+    /// Sets the window length in samples, at least 1.
+    pub fn set_window(&mut self, window: usize) { self.sums.set_window(window); }
+
+    pub fn window(&self) -> usize { self.sums.window }
+}
+
+impl Process<f64> for RollingMean {
+    fn step(&mut self, input: f64) -> f64 { self.sums.step(input).0 }
+}
+
+/// Rolling (population) variance of the signal over the window. The standard
+/// deviation is its square root.
+pub struct RollingVariance { sums: RollingSums }
+
+impl RollingVariance {
+    /// Creates a rolling variance over 64 samples.
+    pub fn new() -> Self { Self { sums: RollingSums::new() } }
+
+    /// Sets the window length in samples, at least 1.
+    pub fn set_window(&mut self, window: usize) { self.sums.set_window(window); }
+
+    pub fn window(&self) -> usize { self.sums.window }
+}
+
+impl Process<f64> for RollingVariance {
+    fn step(&mut self, input: f64) -> f64 {
+        let (mean, mean_sq) = self.sums.step(input);
+        (mean_sq - mean * mean).max(0.0)
+    }
+}
+
+/// Rolling RMS level of the signal over the window.
+pub struct RollingRms { sums: RollingSums }
+
+impl RollingRms {
+    /// Creates a rolling RMS over 64 samples.
+    pub fn new() -> Self { Self { sums: RollingSums::new() } }
+
+    /// Sets the window length in samples, at least 1.
+    pub fn set_window(&mut self, window: usize) { self.sums.set_window(window); }
+
+    pub fn window(&self) -> usize { self.sums.window }
+}
+
+impl Process<f64> for RollingRms {
+    fn step(&mut self, input: f64) -> f64 { self.sums.step(input).1.sqrt() }
+}
@@ -1,16 +1,52 @@
 use std::f64::consts;
 
 use crate::traits::Source;
-use crate::traits::ProcessChain;
 use crate::utils::math::{asym_tri_shaper, par_shaper};
-use crate::core::lin_filter::{BiquadLowPass};
+use crate::core::oversampling::{Downsampler, OversamplingFactor, OversamplingPhase};
 
 // === RAMP CORE ===
 
+/// Used to select how a slave oscillator is synced to its master.
+///
+/// - Hard: the phase of the slave is reset, adds the classic bright, tearing
+///   harmonics when the slave is swept
+/// - Reverse: the direction of the slave is reversed, a softer sync, which
+///   doesn't add discontinuities to continuous waveforms
+#[derive(Clone, Copy, PartialEq)]
+pub enum SyncMode {
+    Hard,
+    Reverse,
+}
+
 /// Phase ramp for driving all oscillators in this module
+///
+/// # Sync
+/// After each step, `wrapped()` tells if the phase wrapped around between the
+/// sample that was just returned and the next one, and how long before the
+/// next sample. Passing that to `sync()` of another oscillator, before stepping
+/// it, syncs it with sub-sample accuracy:
+/// ```
+/// use dsp_lab::core::osc::{BlSawOsc, RampCore, SyncMode};
+/// use dsp_lab::traits::Source;
+/// let mut master = RampCore::new();
+/// master.set_freq(110.0);
+/// let mut slave = BlSawOsc::new();
+/// slave.set_freq(287.0);
+/// for _ in 0..1000 {
+///     master.step();
+///     if let Some(frac) = master.wrapped() {
+///         slave.sync(SyncMode::Hard, frac);
+///     }
+///     let y = slave.step();
+/// }
+/// ```
 pub struct RampCore{
     phase: f64,
     rad_per_sec: f64,
+    // 1 or -1, flipped by reversing sync
+    dir: f64,
+    sync: Option<(SyncMode, f64)>,
+    wrap: Option<f64>,
     pub sr: f64,
 }
 
@@ -23,6 +59,9 @@ impl RampCore {
         Self {
             phase:       0.0,
             rad_per_sec: 440.0 * consts::TAU,
+            dir:         1.0,
+            sync:        None,
+            wrap:        None,
             sr:          44100.0,
         }
     }
@@ -38,30 +77,91 @@ impl RampCore {
         self.phase = phase.rem_euclid(consts::TAU);
     }
 
+    /// Syncs the ramp during the next step, `frac` samples (between 0 and 1)
+    /// before the end of the step, see `SyncMode`. A hard sync counts as a
+    /// wrap, so that syncs can be chained.
+    pub fn sync(&mut self, mode: SyncMode, frac: f64) {
+        self.sync = Some((mode, frac.clamp(0.0, 1.0)));
+    }
+
+    /// If the phase wrapped around during the last step, how many samples
+    /// (between 0 and 1) before the next sample it wrapped.
+    pub fn wrapped(&self) -> Option<f64> { self.wrap }
 
+    // phase increment per sample, as a fraction of a cycle
+    pub(crate) fn norm_phase_inc(&self) -> f64 {
+        self.dir * self.rad_per_sec / self.sr / consts::TAU
+    }
 }
 
 impl Source<f64> for RampCore {
     fn step(&mut self) -> f64 {
         let ret = self.phase;
-        self.phase += self.rad_per_sec/self.sr;
-        self.phase = self.phase.rem_euclid(consts::TAU);
-        return ret;
+        let inc = self.dir * self.rad_per_sec / self.sr;
+        let mut next = ret + inc;
+
+        self.wrap = None;
+        match self.sync.take() {
+            Some((SyncMode::Hard, frac)) => {
+                next = frac * inc;
+                self.wrap = Some(frac);
+            },
+            Some((SyncMode::Reverse, frac)) => {
+                next -= 2.0 * frac * inc;
+                self.dir = -self.dir;
+            },
+            None => {},
+        }
+        if self.wrap.is_none() && inc != 0.0 {
+            if next >= consts::TAU {
+                self.wrap = Some(((next - consts::TAU) / inc).clamp(0.0, 1.0));
+            } else if next < 0.0 {
+                self.wrap = Some((next / inc).clamp(0.0, 1.0));
+            }
+        }
+
+        self.phase = next.rem_euclid(consts::TAU);
+        ret
     }
 }
 
 
 // === BASIC SHAPES ===
 
+// Steps an oversampled ramp once for each sample of `buf`, shaping each phase
+// with `shaper`. A host rate `sync` is applied to the oversampled step it falls
+// in, and the returned wrap, if any, is converted back to the host rate.
+fn step_oversampled(osc: &mut RampCore, sync: Option<(SyncMode, f64)>, buf: &mut [f64],
+    shaper: impl Fn(f64) -> f64) -> Option<f64>
+{
+    let len = buf.len();
+    // position of the sync, in oversampled steps from the start of the step
+    let sync_pos = sync.map(|(mode, frac)| (mode, (1.0 - frac) * len as f64));
+    let mut wrap = None;
+    for (k, x) in buf.iter_mut().enumerate() {
+        if let Some((mode, pos)) = sync_pos {
+            if (pos.floor() as usize).min(len - 1) == k {
+                osc.sync(mode, (k + 1) as f64 - pos);
+            }
+        }
+        *x = shaper(osc.step());
+        if let Some(frac) = osc.wrapped() {
+            wrap = Some((frac + (len - 1 - k) as f64) / len as f64);
+        }
+    }
+    wrap
+}
+
 // TODO: extend morphing so that it can both be a saw and a ramp
 /// Variable symmetry trianlge oscillator. The `asym` control, makes the rising
 /// and falling slopes different, at the extreme (1.0), it turns into a saw wave.
 pub struct AsymTriOsc {
     osc: RampCore,
-    downsampling_lp_1: BiquadLowPass,
-    downsampling_lp_2: BiquadLowPass,
-    downsampling_lp_3: BiquadLowPass,
-    pub oversampling: u8,
+    downsampler: Downsampler,
+    oversampling: OversamplingFactor,
+    sr: f64,
+    sync: Option<(SyncMode, f64)>,
+    wrap: Option<f64>,
     pub asym: f64,
 }
 
@@ -69,22 +169,27 @@ impl AsymTriOsc {
     pub fn new() -> Self {
         Self {
             osc: RampCore::new(),
-            downsampling_lp_1: BiquadLowPass::new(),
-            downsampling_lp_2: BiquadLowPass::new(),
-            downsampling_lp_3: BiquadLowPass::new(),
-            oversampling: 1,
+            downsampler: Downsampler::new(OversamplingFactor::X1, OversamplingPhase::Minimum),
+            oversampling: OversamplingFactor::X1,
+            sr: 44100.0,
+            sync: None,
+            wrap: None,
             asym: 0.0,
         }
     }
 
     pub fn set_sr(&mut self, sr: f64) {
-        self.osc.sr = sr * self.oversampling as f64;
-        self.downsampling_lp_1.set_sr(sr * self.oversampling as f64);
-        self.downsampling_lp_2.set_sr(sr * self.oversampling as f64);
-        self.downsampling_lp_3.set_sr(sr * self.oversampling as f64);
-        self.downsampling_lp_1.cutoff = sr * 0.48;
-        self.downsampling_lp_2.cutoff = sr * 0.48;
-        self.downsampling_lp_3.cutoff = sr * 0.48;
+        self.sr = sr;
+        self.osc.sr = sr * self.oversampling as usize as f64;
+    }
+
+    /// Sets the oversampling factor, the waveform is generated at the higher
+    /// rate and decimated back. This is a method and not a field, because the
+    /// ramp rate and the decimation filters depend on it.
+    pub fn set_oversampling(&mut self, factor: OversamplingFactor) {
+        self.oversampling = factor;
+        self.downsampler = Downsampler::new(factor, OversamplingPhase::Minimum);
+        self.set_sr(self.sr);
     }
 
     pub fn set_freq(&mut self, freq: f64) {
@@ -94,19 +199,25 @@ impl AsymTriOsc {
     pub fn set_phase(&mut self, phase: f64) {
         self.osc.set_phase(phase);
     }
+
+    /// Syncs the oscillator during the next step, see `RampCore::sync()`.
+    pub fn sync(&mut self, mode: SyncMode, frac: f64) {
+        self.sync = Some((mode, frac.clamp(0.0, 1.0)));
+    }
+
+    /// Wrap of the last step, for syncing other oscillators, see
+    /// `RampCore::wrapped()`.
+    pub fn wrapped(&self) -> Option<f64> { self.wrap }
 }
 
 impl Source<f64> for AsymTriOsc {
     fn step(&mut self) -> f64 {
-        let mut res = 0.0;
-        for _ in 0..self.oversampling {
-            res = ProcessChain::new(asym_tri_shaper(self.osc.step(), self.asym))
-                .pipe(&mut self.downsampling_lp_1)
-                .pipe(&mut self.downsampling_lp_2)
-                .pipe(&mut self.downsampling_lp_3)
-                .consume();
-        }
-        res
+        let len = self.oversampling as usize;
+        let mut buf = [0.0; 16];
+        let asym = self.asym;
+        self.wrap = step_oversampled(&mut self.osc, self.sync.take(), &mut buf[..len],
+            |phase| asym_tri_shaper(phase, asym));
+        self.downsampler.step(&buf[..len])
     }
 }
 
@@ -115,10 +226,11 @@ impl Source<f64> for AsymTriOsc {
 /// a bit of saturation. Can actually sound very nice as an analog sine.
 pub struct ParOsc {
     osc: RampCore,
-    downsampling_lp_1: BiquadLowPass,
-    downsampling_lp_2: BiquadLowPass,
-    downsampling_lp_3: BiquadLowPass,
-    pub oversampling: u8,
+    downsampler: Downsampler,
+    oversampling: OversamplingFactor,
+    sr: f64,
+    sync: Option<(SyncMode, f64)>,
+    wrap: Option<f64>,
     pub asym: f64,
 }
 
@@ -126,22 +238,27 @@ impl ParOsc {
     pub fn new() -> Self {
         Self {
             osc: RampCore::new(),
-            downsampling_lp_1: BiquadLowPass::new(),
-            downsampling_lp_2: BiquadLowPass::new(),
-            downsampling_lp_3: BiquadLowPass::new(),
-            oversampling: 1,
+            downsampler: Downsampler::new(OversamplingFactor::X1, OversamplingPhase::Minimum),
+            oversampling: OversamplingFactor::X1,
+            sr: 44100.0,
+            sync: None,
+            wrap: None,
             asym: 0.0,
         }
     }
 
     pub fn set_sr(&mut self, sr: f64) {
-        self.osc.sr = sr * self.oversampling as f64;
-        self.downsampling_lp_1.set_sr(sr * self.oversampling as f64);
-        self.downsampling_lp_2.set_sr(sr * self.oversampling as f64);
-        self.downsampling_lp_3.set_sr(sr * self.oversampling as f64);
-        self.downsampling_lp_1.cutoff = sr * 0.48;
-        self.downsampling_lp_2.cutoff = sr * 0.48;
-        self.downsampling_lp_3.cutoff = sr * 0.48;
+        self.sr = sr;
+        self.osc.sr = sr * self.oversampling as usize as f64;
+    }
+
+    /// Sets the oversampling factor, the waveform is generated at the higher
+    /// rate and decimated back. This is a method and not a field, because the
+    /// ramp rate and the decimation filters depend on it.
+    pub fn set_oversampling(&mut self, factor: OversamplingFactor) {
+        self.oversampling = factor;
+        self.downsampler = Downsampler::new(factor, OversamplingPhase::Minimum);
+        self.set_sr(self.sr);
     }
 
     pub fn set_freq(&mut self, freq: f64) {
@@ -151,21 +268,312 @@ impl ParOsc {
     pub fn set_phase(&mut self, phase: f64) {
         self.osc.set_phase(phase);
     }
+
+    /// Syncs the oscillator during the next step, see `RampCore::sync()`.
+    pub fn sync(&mut self, mode: SyncMode, frac: f64) {
+        self.sync = Some((mode, frac.clamp(0.0, 1.0)));
+    }
+
+    /// Wrap of the last step, for syncing other oscillators, see
+    /// `RampCore::wrapped()`.
+    pub fn wrapped(&self) -> Option<f64> { self.wrap }
 }
 
 impl Source<f64> for ParOsc {
     fn step(&mut self) -> f64 {
-        let mut res = 0.0;
-        for _ in 0..self.oversampling {
-            res = ProcessChain::new(par_shaper(self.osc.step()))
-                .pipe(&mut self.downsampling_lp_1)
-                .pipe(&mut self.downsampling_lp_2)
-                .pipe(&mut self.downsampling_lp_3)
-                .consume();
+        let len = self.oversampling as usize;
+        let mut buf = [0.0; 16];
+        self.wrap = step_oversampled(&mut self.osc, self.sync.take(), &mut buf[..len],
+            par_shaper);
+        self.downsampler.step(&buf[..len])
+    }
+}
+
+
+// === BAND-LIMITED SHAPES ===
+
+// Residual between a band-limited and a naive unit step, at `x` samples from the
+// step, two-point polyBLEP.
+fn poly_blep(x: f64) -> f64 {
+    if x <= -1.0 || x >= 1.0 { 0.0 }
+    else if x < 0.0 { 0.5 * (x + 1.0) * (x + 1.0) }
+    else { -0.5 * (1.0 - x) * (1.0 - x) }
+}
+
+// Residual between a band-limited and a naive unit ramp, i.e. the integral of
+// `poly_blep`, two-point polyBLAMP.
+fn poly_blamp(x: f64) -> f64 {
+    let a = 1.0 - x.abs();
+    if a <= 0.0 { 0.0 } else { a * a * a / 6.0 }
+}
+
+// Band-limiting correction for a discontinuity of the waveform at phase `at`,
+// for an oscillator at phase `t` with phase increment `dt`, both as fractions
+// of a cycle. `step` is the jump of the waveform, and `slope` the change of its
+// slope per cycle. Also works when running backwards, for through-zero FM.
+fn bl_correction(t: f64, dt: f64, at: f64, step: f64, slope: f64) -> f64 {
+    let adt = dt.abs().max(1e-12);
+    let x = ((t - at + 0.5).rem_euclid(1.0) - 0.5) / adt;
+    step * poly_blep(x) + slope * adt * poly_blamp(x)
+}
+
+// Band-limiting correction for a sync of the ramp during a step from phase `t`
+// with increment `dt`, of a waveform whose value and slope per cycle at a phase
+// are given by `shape`. Returns the correction of the current sample, and
+// stores the one of the next sample in `next`. After a hard sync, the ramp is
+// right past phase 0, so `bl_correction` of the next sample already corrects
+// the jump of the waveform at phase 0, which is taken out.
+fn sync_correction(sync: Option<(SyncMode, f64)>, t: f64, dt: f64, next: &mut f64,
+    shape: impl Fn(f64) -> (f64, f64)) -> f64
+{
+    let ret = *next;
+    *next = 0.0;
+    let (mode, frac) = match sync {
+        Some(sync) => sync,
+        None => return ret,
+    };
+
+    // waveform at the sync
+    let (y, slope) = shape((t + (1.0 - frac) * dt).rem_euclid(1.0));
+    let (step, kink, own_step, own_kink) = match mode {
+        SyncMode::Hard => {
+            let (y_0, slope_0) = shape(0.0);
+            let (y_1, slope_1) = shape(1.0);
+            let (y_after, slope_after) = if dt >= 0.0 { (y_0, slope_0) } else { (y_1, slope_1) };
+            (y_after - y, (slope_after - slope) * dt,
+                (y_0 - y_1) * dt.signum(), (slope_0 - slope_1) * dt.abs())
+        },
+        SyncMode::Reverse => (0.0, -2.0 * slope * dt, 0.0, 0.0),
+    };
+
+    *next = (step - own_step) * poly_blep(frac) + (kink - own_kink) * poly_blamp(frac);
+    ret + step * poly_blep(frac - 1.0) + kink * poly_blamp(frac - 1.0)
+}
+
+/// Band-limited sawtooth oscillator, rising from -1 to 1, with polyBLEP.
+///
+/// The frequency can be changed at every sample for FM, including negative
+/// frequencies for through-zero FM, without aliasing from the corrections.
+///
+/// # Examples
+/// ```
+/// use dsp_lab::core::osc::BlSawOsc;
+/// use dsp_lab::traits::Source;
+/// let mut saw = BlSawOsc::new();
+/// saw.set_freq(110.0);
+/// let y = saw.step();
+/// ```
+pub struct BlSawOsc {
+    osc: RampCore,
+    sync_next: f64,
+}
+
+impl BlSawOsc {
+    pub fn new() -> Self {
+        Self {
+            osc: RampCore::new(),
+            sync_next: 0.0,
         }
-        res
+    }
+
+    pub fn set_sr(&mut self, sr: f64) { self.osc.sr = sr; }
+
+    pub fn set_freq(&mut self, freq: f64) { self.osc.set_freq(freq); }
+
+    pub fn set_phase(&mut self, phase: f64) { self.osc.set_phase(phase); }
+
+    /// Syncs the oscillator during the next step, see `RampCore::sync()`.
+    pub fn sync(&mut self, mode: SyncMode, frac: f64) { self.osc.sync(mode, frac); }
+
+    /// Wrap of the last step, for syncing other oscillators, see
+    /// `RampCore::wrapped()`.
+    pub fn wrapped(&self) -> Option<f64> { self.osc.wrapped() }
+}
+
+impl Source<f64> for BlSawOsc {
+    fn step(&mut self) -> f64 {
+        let dt = self.osc.norm_phase_inc();
+        let sync = self.osc.sync;
+        let t = self.osc.step() / consts::TAU;
+        let shape = |t: f64| (2.0 * t - 1.0, 2.0);
+        shape(t).0 + bl_correction(t, dt, 0.0, -2.0, 0.0)
+            + sync_correction(sync, t, dt, &mut self.sync_next, shape)
     }
 }
 
 
-// TODO: pulse oscillator
\ No newline at end of file
+/// Band-limited pulse oscillator, with polyBLEP.
+///
+/// - `width`: fraction of the cycle spent high, clamped between 0.01 and 0.99,
+///   0.5 is a square wave. Can be modulated at every sample for PWM.
+///
+/// # Caveats
+/// The output is not DC-free for widths other than 0.5.
+///
+/// # Examples
+/// ```
+/// use dsp_lab::core::osc::BlPulseOsc;
+/// use dsp_lab::traits::Source;
+/// let mut pulse = BlPulseOsc::new();
+/// pulse.width = 0.25;
+/// let y = pulse.step();
+/// ```
+pub struct BlPulseOsc {
+    osc: RampCore,
+    sync_next: f64,
+    pub width: f64,
+}
+
+impl BlPulseOsc {
+    /// Creates a square wave oscillator.
+    pub fn new() -> Self {
+        Self {
+            osc: RampCore::new(),
+            sync_next: 0.0,
+            width: 0.5,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) { self.osc.sr = sr; }
+
+    pub fn set_freq(&mut self, freq: f64) { self.osc.set_freq(freq); }
+
+    pub fn set_phase(&mut self, phase: f64) { self.osc.set_phase(phase); }
+
+    /// Syncs the oscillator during the next step, see `RampCore::sync()`.
+    pub fn sync(&mut self, mode: SyncMode, frac: f64) { self.osc.sync(mode, frac); }
+
+    /// Wrap of the last step, for syncing other oscillators, see
+    /// `RampCore::wrapped()`.
+    pub fn wrapped(&self) -> Option<f64> { self.osc.wrapped() }
+}
+
+impl Source<f64> for BlPulseOsc {
+    fn step(&mut self) -> f64 {
+        let dt = self.osc.norm_phase_inc();
+        let sync = self.osc.sync;
+        let t = self.osc.step() / consts::TAU;
+        let w = self.width.clamp(0.01, 0.99);
+        let shape = |t: f64| (if t < w { 1.0 } else { -1.0 }, 0.0);
+        shape(t).0 + bl_correction(t, dt, 0.0, 2.0, 0.0) + bl_correction(t, dt, w, -2.0, 0.0)
+            + sync_correction(sync, t, dt, &mut self.sync_next, shape)
+    }
+}
+
+
+/// Band-limited triangle oscillator, with polyBLAMP.
+///
+/// # Examples
+/// ```
+/// use dsp_lab::core::osc::BlTriOsc;
+/// use dsp_lab::traits::Source;
+/// let mut tri = BlTriOsc::new();
+/// tri.set_freq(3520.0);
+/// let y = tri.step();
+/// ```
+pub struct BlTriOsc {
+    osc: RampCore,
+    sync_next: f64,
+}
+
+impl BlTriOsc {
+    pub fn new() -> Self {
+        Self {
+            osc: RampCore::new(),
+            sync_next: 0.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) { self.osc.sr = sr; }
+
+    pub fn set_freq(&mut self, freq: f64) { self.osc.set_freq(freq); }
+
+    pub fn set_phase(&mut self, phase: f64) { self.osc.set_phase(phase); }
+
+    /// Syncs the oscillator during the next step, see `RampCore::sync()`.
+    pub fn sync(&mut self, mode: SyncMode, frac: f64) { self.osc.sync(mode, frac); }
+
+    /// Wrap of the last step, for syncing other oscillators, see
+    /// `RampCore::wrapped()`.
+    pub fn wrapped(&self) -> Option<f64> { self.osc.wrapped() }
+}
+
+impl Source<f64> for BlTriOsc {
+    fn step(&mut self) -> f64 {
+        let dt = self.osc.norm_phase_inc();
+        let sync = self.osc.sync;
+        let t = self.osc.step() / consts::TAU;
+        let shape = |t: f64| if t < 0.5 { (4.0 * t - 1.0, 4.0) } else { (3.0 - 4.0 * t, -4.0) };
+        shape(t).0 + bl_correction(t, dt, 0.0, 0.0, 8.0) + bl_correction(t, dt, 0.5, 0.0, -8.0)
+            + sync_correction(sync, t, dt, &mut self.sync_next, shape)
+    }
+}
+
+
+/// Band-limited morphing oscillator, from falling ramp, through triangle, to
+/// rising sawtooth, with polyBLAMP.
+///
+/// - `shape`: between -1 and 1, -1 is a falling ramp, 0 is a triangle and 1 is
+///   a rising sawtooth
+///
+/// # Caveats
+/// At the extremes, the short slope of the waveform is limited to two samples,
+/// so that the corrections don't overlap, which band-limits it like a polyBLEP
+/// edge.
+///
+/// # Examples
+/// ```
+/// use dsp_lab::core::osc::BlMorphOsc;
+/// use dsp_lab::traits::Source;
+/// let mut osc = BlMorphOsc::new();
+/// osc.shape = 0.7;
+/// let y = osc.step();
+/// ```
+pub struct BlMorphOsc {
+    osc: RampCore,
+    sync_next: f64,
+    pub shape: f64,
+}
+
+impl BlMorphOsc {
+    /// Creates a triangle oscillator.
+    pub fn new() -> Self {
+        Self {
+            osc: RampCore::new(),
+            sync_next: 0.0,
+            shape: 0.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) { self.osc.sr = sr; }
+
+    pub fn set_freq(&mut self, freq: f64) { self.osc.set_freq(freq); }
+
+    pub fn set_phase(&mut self, phase: f64) { self.osc.set_phase(phase); }
+
+    /// Syncs the oscillator during the next step, see `RampCore::sync()`.
+    pub fn sync(&mut self, mode: SyncMode, frac: f64) { self.osc.sync(mode, frac); }
+
+    /// Wrap of the last step, for syncing other oscillators, see
+    /// `RampCore::wrapped()`.
+    pub fn wrapped(&self) -> Option<f64> { self.osc.wrapped() }
+}
+
+impl Source<f64> for BlMorphOsc {
+    fn step(&mut self) -> f64 {
+        let dt = self.osc.norm_phase_inc();
+        let sync = self.osc.sync;
+        let t = self.osc.step() / consts::TAU;
+
+        // position of the peak, each slope lasts at least two samples
+        let margin = (2.0 * dt.abs()).min(0.5);
+        let p = (0.5 * (self.shape + 1.0)).clamp(margin, 1.0 - margin);
+        let rise = 2.0 / p;
+        let fall = -2.0 / (1.0 - p);
+
+        let shape = |t: f64| if t < p { (rise * t - 1.0, rise) } else { (1.0 + fall * (t - p), fall) };
+        shape(t).0 + bl_correction(t, dt, 0.0, 0.0, rise - fall)
+            + bl_correction(t, dt, p, 0.0, fall - rise)
+            + sync_correction(sync, t, dt, &mut self.sync_next, shape)
+    }
+}
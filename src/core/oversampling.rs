@@ -0,0 +1,431 @@
+//! Oversampling, runs processes at a multiple of the host sample rate.
+//!
+//! Non-linear processes generate harmonics above nyquist, which fold back as
+//! aliasing. Running them at a higher sample rate leaves room for the harmonics,
+//! which are then filtered out when going back to the host sample rate.
+//!
+//! The sample rate is changed in steps of 2, with cascaded halfband filters in
+//! polyphase form, so that each filter only computes the samples that are kept.
+//! The first stage, closest to the host sample rate, is the steepest, as it
+//! needs to keep the whole audible band, later stages only need to reject the
+//! images of the audible band and are much cheaper.
+//!
+//! + `Upsampler` and `Downsampler`: the two halves of oversampling, for when the
+//!   processing at the higher rate can't be expressed as a `Process`, like
+//!   oscillators
+//! + `Oversampler`: wraps any `Process` and runs it at the higher rate
+//!
+//! # Caveats
+//! The filters pass everything up to 20kHz at 44100Hz sample rate within
+//! 0.01dB, and reject aliasing by more than 95dB for linear phase and 75dB for
+//! minimum phase. The processes in the oversampled section see the higher sample rate,
+//! so their `set_sr()` must be called with the oversampled rate.
+
+use std::f64::consts;
+
+use crate::traits::Process;
+use crate::utils::math::win_kaiser;
+
+// taps per polyphase branch of the linear phase halfband filters, for the first
+// and later stages. The filters have 4 * N - 1 taps.
+const FIR_BRANCH_LEN_FIRST: usize = 32;
+const FIR_BRANCH_LEN_OTHER: usize = 8;
+
+// kaiser window shape of the linear phase halfband filters
+const FIR_KAISER_BETA: f64 = 10.0;
+
+// number of all-pass coefficients and transition bandwidth (as a fraction of
+// the oversampled rate) of the minimum phase halfband filters, for the first and
+// later stages.
+const IIR_COEFS_FIRST: usize = 10;
+const IIR_TRANSITION_FIRST: f64 = 0.025;
+const IIR_COEFS_OTHER: usize = 4;
+const IIR_TRANSITION_OTHER: f64 = 0.13;
+
+/// Used to select the oversampling factor.
+#[derive(Clone, Copy, PartialEq)]
+pub enum OversamplingFactor {
+    X1 = 1,
+    X2 = 2,
+    X4 = 4,
+    X8 = 8,
+    X16 = 16,
+}
+
+/// Used to select the phase response of the oversampling filters.
+///
+/// - Linear: FIR halfband filters, no phase distortion, but more latency
+/// - Minimum: IIR halfband filters made of all-pass pairs, much lower latency
+///   and cost, at the price of phase distortion near nyquist
+#[derive(Clone, Copy, PartialEq)]
+pub enum OversamplingPhase {
+    Linear,
+    Minimum,
+}
+
+// number of halfband stages for a factor
+fn num_stages(factor: OversamplingFactor) -> usize {
+    (factor as usize).trailing_zeros() as usize
+}
+
+
+// === HALFBAND FILTERS ===
+
+// Non-zero taps of a kaiser-windowed halfband filter with `branch_len` taps per
+// polyphase branch, i.e. the even taps h[0], h[2], ... h[4N - 2]. The remaining
+// taps are zero, except for the center one which is 0.5.
+fn fir_halfband(branch_len: usize) -> Vec<f64> {
+    let len = 4 * branch_len - 1;
+    let center = (len / 2) as f64;
+    let mut taps: Vec<f64> = (0..2 * branch_len).map(|j| {
+        let n = 2.0 * j as f64 - center;
+        let window = win_kaiser(n + center, 2.0 * center, FIR_KAISER_BETA);
+        (consts::FRAC_PI_2 * n).sin() / (consts::PI * n) * window
+    }).collect();
+
+    // normalize, so that the branch sums to exactly 0.5, for unity gain at DC
+    let sum: f64 = taps.iter().sum();
+    for t in taps.iter_mut() { *t *= 0.5 / sum; }
+    taps
+}
+
+// All-pass coefficients of an elliptic halfband filter made of two all-pass
+// chains, with the given transition bandwidth. Even coefficients belong to the
+// first chain, odd ones to the second chain. Design from Laurent de Soras' HIIR:
+// <http://ldesoras.free.fr/prod.html#src_hiir>
+fn iir_halfband(num_coefs: usize, transition: f64) -> Vec<f64> {
+    let k = ((1.0 - 2.0 * transition) * consts::FRAC_PI_4).tan().powi(2);
+    let k_root = (1.0 - k * k).powf(0.25);
+    let e = 0.5 * (1.0 - k_root) / (1.0 + k_root);
+    let e4 = e.powi(4);
+    let q = e * (1.0 + e4 * (2.0 + e4 * (15.0 + 150.0 * e4)));
+
+    let order = (2 * num_coefs + 1) as f64;
+    (0..num_coefs).map(|i| {
+        let c = (i + 1) as f64;
+        let mut num = 0.0;
+        let mut sign = 1.0;
+        for n in 0.. {
+            let term = q.powi(n * (n + 1)) * ((2 * n + 1) as f64 * c * consts::PI / order).sin();
+            num += sign * term;
+            sign = -sign;
+            if term.abs() < 1e-100 { break; }
+        }
+        let mut den = 0.0;
+        let mut sign = -1.0;
+        for n in 1.. {
+            let term = q.powi(n * n) * (2.0 * n as f64 * c * consts::PI / order).cos();
+            den += sign * term;
+            sign = -sign;
+            if term.abs() < 1e-100 { break; }
+        }
+        let w = num * q.powf(0.25) / (den + 0.5);
+        let w2 = w * w;
+        let x = ((1.0 - w2 * k) * (1.0 - w2 / k)).sqrt() / (1.0 + w2);
+        (1.0 - x) / (1.0 + x)
+    }).collect()
+}
+
+// Linear phase halfband filter in polyphase form, used either to upsample or to
+// downsample by 2. Only the history of one direction is used.
+struct HalfbandFir {
+    taps: Vec<f64>,
+    // history of the input, or of the even input samples when downsampling
+    x: Vec<f64>,
+    // history of the odd input samples when downsampling
+    x_odd: Vec<f64>,
+}
+
+impl HalfbandFir {
+    fn new(branch_len: usize) -> Self {
+        Self {
+            taps: fir_halfband(branch_len),
+            x: vec![0.0; 2 * branch_len],
+            x_odd: vec![0.0; branch_len + 1],
+        }
+    }
+
+    // latency in samples at the higher rate, for each direction
+    fn latency(&self) -> (f64, f64) {
+        let l = (self.taps.len() - 1) as f64;
+        (l, l)
+    }
+
+    fn up(&mut self, input: f64) -> (f64, f64) {
+        let n = self.x.len();
+        self.x.copy_within(0..n - 1, 1);
+        self.x[0] = input;
+
+        // the odd branch is just the center tap, a pure delay
+        let even = 2.0 * self.taps.iter().zip(self.x.iter()).map(|(h, x)| h * x).sum::<f64>();
+        (even, self.x[n / 2 - 1])
+    }
+
+    fn down(&mut self, input: (f64, f64)) -> f64 {
+        let n = self.x.len();
+        self.x.copy_within(0..n - 1, 1);
+        self.x[0] = input.0;
+        let n_odd = self.x_odd.len();
+        self.x_odd.copy_within(0..n_odd - 1, 1);
+        self.x_odd[0] = input.1;
+
+        self.taps.iter().zip(self.x.iter()).map(|(h, x)| h * x).sum::<f64>()
+            + 0.5 * self.x_odd[n_odd - 1]
+    }
+}
+
+// Minimum phase halfband filter made of two chains of first order all-pass
+// sections in z^-2, used either to upsample or to downsample by 2.
+struct HalfbandIir {
+    coefs: Vec<f64>,
+    x: Vec<f64>,
+    y: Vec<f64>,
+}
+
+impl HalfbandIir {
+    fn new(num_coefs: usize, transition: f64) -> Self {
+        Self {
+            coefs: iir_halfband(num_coefs, transition),
+            x: vec![0.0; num_coefs],
+            y: vec![0.0; num_coefs],
+        }
+    }
+
+    // group delay at DC in samples at the higher rate, for each direction. Each
+    // section (a + z^-2) / (1 + a z^-2) delays by 2 (1 - a) / (1 + a), and the
+    // output averages the two chains, one of which is offset by one sample.
+    fn latency(&self) -> (f64, f64) {
+        let chains: f64 = self.coefs.iter().map(|a| 2.0 * (1.0 - a) / (1.0 + a)).sum();
+        (0.5 * chains + 0.5, 0.5 * chains - 0.5)
+    }
+
+    // runs the two chains, `a` through the even coefficients and `b` through
+    // the odd ones
+    fn chains(&mut self, mut a: f64, mut b: f64) -> (f64, f64) {
+        for (i, c) in self.coefs.iter().enumerate() {
+            let input = if i % 2 == 0 { a } else { b };
+            let y = (input - self.y[i]) * c + self.x[i];
+            self.x[i] = input;
+            self.y[i] = y;
+            if i % 2 == 0 { a = y; } else { b = y; }
+        }
+        (a, b)
+    }
+
+    fn up(&mut self, input: f64) -> (f64, f64) {
+        self.chains(input, input)
+    }
+
+    fn down(&mut self, input: (f64, f64)) -> f64 {
+        let (a, b) = self.chains(input.1, input.0);
+        0.5 * (a + b)
+    }
+}
+
+enum Halfband {
+    Fir(HalfbandFir),
+    Iir(HalfbandIir),
+}
+
+impl Halfband {
+    // halfband filter for the stage at `index`, 0 being closest to the host rate
+    fn new(phase: OversamplingPhase, index: usize) -> Self {
+        match (phase, index) {
+            (OversamplingPhase::Linear, 0) => Halfband::Fir(HalfbandFir::new(FIR_BRANCH_LEN_FIRST)),
+            (OversamplingPhase::Linear, _) => Halfband::Fir(HalfbandFir::new(FIR_BRANCH_LEN_OTHER)),
+            (OversamplingPhase::Minimum, 0) => Halfband::Iir(HalfbandIir::new(IIR_COEFS_FIRST, IIR_TRANSITION_FIRST)),
+            (OversamplingPhase::Minimum, _) => Halfband::Iir(HalfbandIir::new(IIR_COEFS_OTHER, IIR_TRANSITION_OTHER)),
+        }
+    }
+
+    fn latency(&self) -> (f64, f64) {
+        match self {
+            Halfband::Fir(f) => f.latency(),
+            Halfband::Iir(f) => f.latency(),
+        }
+    }
+
+    fn up(&mut self, input: f64) -> (f64, f64) {
+        match self {
+            Halfband::Fir(f) => f.up(input),
+            Halfband::Iir(f) => f.up(input),
+        }
+    }
+
+    fn down(&mut self, input: (f64, f64)) -> f64 {
+        match self {
+            Halfband::Fir(f) => f.down(input),
+            Halfband::Iir(f) => f.down(input),
+        }
+    }
+}
+
+
+// === UP AND DOWN SAMPLING ===
+
+/// Upsampler, turns each input sample into `factor` samples at the higher rate.
+///
+/// # Examples
+/// ```
+/// use dsp_lab::core::oversampling::{Upsampler, OversamplingFactor, OversamplingPhase};
+/// let mut up = Upsampler::new(OversamplingFactor::X4, OversamplingPhase::Linear);
+/// let block = up.step(1.0);
+/// assert!(block.len() == 4);
+/// ```
+pub struct Upsampler {
+    stages: Vec<Halfband>,
+    buf: [f64; 16],
+    factor: OversamplingFactor,
+}
+
+impl Upsampler {
+    pub fn new(factor: OversamplingFactor, phase: OversamplingPhase) -> Self {
+        Self {
+            stages: (0..num_stages(factor)).map(|i| Halfband::new(phase, i)).collect(),
+            buf: [0.0; 16],
+            factor,
+        }
+    }
+
+    pub fn factor(&self) -> OversamplingFactor { self.factor }
+
+    /// Latency in samples at the host rate. For minimum phase this is the
+    /// group delay at DC, higher frequencies are delayed more.
+    pub fn latency(&self) -> f64 {
+        self.stages.iter().enumerate()
+            .map(|(i, s)| s.latency().0 / (2 << i) as f64)
+            .sum()
+    }
+
+    /// Upsamples one sample.
+    /// # Returns
+    /// - `factor` samples at the higher rate, oldest first
+    pub fn step(&mut self, input: f64) -> &[f64] {
+        self.buf[0] = input;
+        let mut len = 1;
+        for stage in self.stages.iter_mut() {
+            // the filters need the samples in order, so work on a copy
+            let prev = self.buf;
+            for (i, x) in prev.iter().take(len).enumerate() {
+                let (a, b) = stage.up(*x);
+                self.buf[2 * i] = a;
+                self.buf[2 * i + 1] = b;
+            }
+            len *= 2;
+        }
+        &self.buf[..len]
+    }
+}
+
+/// Downsampler, turns each block of `factor` samples at the higher rate into a
+/// single output sample, filtering out everything above the host nyquist.
+///
+/// # Examples
+/// ```
+/// use dsp_lab::core::oversampling::{Downsampler, OversamplingFactor, OversamplingPhase};
+/// let mut down = Downsampler::new(OversamplingFactor::X2, OversamplingPhase::Minimum);
+/// let y = down.step(&[1.0, 1.0]);
+/// ```
+pub struct Downsampler {
+    stages: Vec<Halfband>,
+    buf: [f64; 16],
+    factor: OversamplingFactor,
+}
+
+impl Downsampler {
+    pub fn new(factor: OversamplingFactor, phase: OversamplingPhase) -> Self {
+        Self {
+            stages: (0..num_stages(factor)).map(|i| Halfband::new(phase, i)).collect(),
+            buf: [0.0; 16],
+            factor,
+        }
+    }
+
+    pub fn factor(&self) -> OversamplingFactor { self.factor }
+
+    /// Latency in samples at the host rate. For minimum phase this is the
+    /// group delay at DC, higher frequencies are delayed more.
+    pub fn latency(&self) -> f64 {
+        self.stages.iter().enumerate()
+            .map(|(i, s)| s.latency().1 / (2 << i) as f64)
+            .sum()
+    }
+
+    /// Downsamples a block of samples at the higher rate, oldest first.
+    ///
+    /// # Panics
+    /// If `input` is shorter than `factor`. Extra samples are ignored.
+    pub fn step(&mut self, input: &[f64]) -> f64 {
+        let mut len = self.factor as usize;
+        self.buf[..len].copy_from_slice(&input[..len]);
+        for stage in self.stages.iter_mut().rev() {
+            len /= 2;
+            for i in 0..len {
+                self.buf[i] = stage.down((self.buf[2 * i], self.buf[2 * i + 1]));
+            }
+        }
+        self.buf[0]
+    }
+}
+
+
+// === OVERSAMPLER ===
+
+/// Runs a process at `factor` times the host sample rate.
+///
+/// The wrapped process is accessible through the `process` field, remember to
+/// set its sample rate to the oversampled rate.
+///
+/// # Examples
+/// ```
+/// use dsp_lab::core::oversampling::{Oversampler, OversamplingFactor, OversamplingPhase};
+/// use dsp_lab::core::waveshapers::{Adaa1, VarClip};
+/// use dsp_lab::traits::Process;
+/// let mut clip = Oversampler::new(Adaa1::new(VarClip::new()),
+///     OversamplingFactor::X4, OversamplingPhase::Linear);
+/// clip.process.curve.hardness = 0.9;
+/// let y = clip.step(3.0);
+/// let latency = clip.latency();
+/// ```
+pub struct Oversampler<P: Process<f64>> {
+    up: Upsampler,
+    down: Downsampler,
+    buf: [f64; 16],
+    pub process: P,
+}
+
+impl<P: Process<f64>> Oversampler<P> {
+    pub fn new(process: P, factor: OversamplingFactor, phase: OversamplingPhase) -> Self {
+        Self {
+            up: Upsampler::new(factor, phase),
+            down: Downsampler::new(factor, phase),
+            buf: [0.0; 16],
+            process,
+        }
+    }
+
+    /// Changes the oversampling factor and phase response. This is a method
+    /// and not a field, because the filters have to be rebuilt, which allocates
+    /// and clears their state.
+    pub fn set_oversampling(&mut self, factor: OversamplingFactor, phase: OversamplingPhase) {
+        self.up = Upsampler::new(factor, phase);
+        self.down = Downsampler::new(factor, phase);
+    }
+
+    pub fn factor(&self) -> OversamplingFactor { self.up.factor() }
+
+    /// Latency of the up and down sampling filters in samples at the host rate,
+    /// not including the latency of the wrapped process itself.
+    pub fn latency(&self) -> f64 { self.up.latency() + self.down.latency() }
+}
+
+impl<P: Process<f64>> Process<f64> for Oversampler<P> {
+    fn step(&mut self, input: f64) -> f64 {
+        let len = self.up.factor() as usize;
+        let block = self.up.step(input);
+        for (y, x) in self.buf.iter_mut().zip(block.iter()) {
+            *y = self.process.step(*x);
+        }
+        self.down.step(&self.buf[..len])
+    }
+}
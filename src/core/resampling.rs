@@ -0,0 +1,241 @@
+//! Sample rate conversion by arbitrary, and time-varying, ratios.
+//!
+//! + `Resampler`: streaming converter, samples are pushed at one rate and
+//!   pulled at another
+//! + `FixedRate`: wraps any `Process` and runs it at a fixed internal sample
+//!   rate, whatever the host sample rate, for processes that are designed for a
+//!   specific rate
+//!
+//! Conversion uses a kaiser-windowed sinc kernel, stored as a table of finely
+//! spaced polyphase filters and linearly interpolated between them, so that
+//! output samples can land anywhere between input samples. When converting
+//! down, the kernel is stretched to lower its cutoff below the output nyquist.
+//!
+//! # Caveats
+//! The kernel passes everything up to about 80% of the lower of the two
+//! nyquist frequencies and rejects aliasing and images by about 90dB, the band
+//! in between is the transition. The ratio is limited between 1/16 and 16.
+
+use std::collections::VecDeque;
+use std::f64::consts;
+
+use crate::traits::Process;
+use crate::core::RawRingBuffer;
+use crate::utils::math::win_kaiser;
+
+// zero crossings on each side of the kernel, the kernel has twice as many taps
+// when converting up, and more when converting down.
+const HALF_LEN: usize = 32;
+
+// kernel table resolution, in points between zero crossings
+const PHASES: usize = 256;
+
+// cutoff of the kernel relative to nyquist, and kaiser window shape
+const CUTOFF: f64 = 0.91;
+const KAISER_BETA: f64 = 9.0;
+
+// limits of the conversion ratio
+const MAX_RATIO: f64 = 16.0;
+
+// history length, enough for the kernel stretched by MAX_RATIO
+const HIST_LEN: usize = 2048;
+
+/// Streaming sample rate converter.
+///
+/// Input samples are fed with `push()`, and output samples become available
+/// through `pop()` as soon as enough input has been pushed to compute them. The
+/// ratio can change at any time, for varispeed or for following a drifting
+/// clock, the converter keeps its position in time and doesn't click.
+///
+/// # Examples
+/// Converting from 48kHz to 44.1kHz:
+/// ```
+/// use dsp_lab::core::resampling::Resampler;
+/// let mut rs = Resampler::new();
+/// rs.set_rates(48000.0, 44100.0);
+/// let mut out = vec![];
+/// for n in 0..4800 {
+///     rs.push((n as f64 * 0.01).sin());
+///     while let Some(y) = rs.pop() {
+///         out.push(y);
+///     }
+/// }
+/// ```
+pub struct Resampler {
+    hist: RawRingBuffer<HIST_LEN>,
+    // one side of the symmetric kernel, HALF_LEN * PHASES + 1 points, with an
+    // extra zero so that interpolation never reads past the end
+    table: Vec<f64>,
+    // time of the next output, in input samples, relative to the newest input
+    time: f64,
+    ratio: f64,
+}
+
+impl Resampler {
+    /// Creates a converter with a ratio of 1.
+    pub fn new() -> Self {
+        let len = HALF_LEN * PHASES;
+        let mut table: Vec<f64> = (0..=len).map(|k| {
+            let x = k as f64 / PHASES as f64;
+            let sinc = if k == 0 { 1.0 } else {
+                (consts::PI * CUTOFF * x).sin() / (consts::PI * CUTOFF * x)
+            };
+            CUTOFF * sinc * win_kaiser(x + HALF_LEN as f64, 2.0 * HALF_LEN as f64, KAISER_BETA)
+        }).collect();
+        table.push(0.0);
+
+        Self {
+            hist: RawRingBuffer::<HIST_LEN>::new(),
+            table,
+            // the first input lands on time 0
+            time: 1.0,
+            ratio: 1.0,
+        }
+    }
+
+    /// Sets the conversion ratio, as output rate over input rate, clamped
+    /// between 1/16 and 16. This is a method and not a field, because of the
+    /// clamping.
+    pub fn set_ratio(&mut self, ratio: f64) {
+        self.ratio = ratio.clamp(1.0 / MAX_RATIO, MAX_RATIO);
+    }
+
+    /// Sets the conversion ratio from the input and output sample rates.
+    pub fn set_rates(&mut self, sr_in: f64, sr_out: f64) {
+        self.set_ratio(sr_out / sr_in);
+    }
+
+    pub fn ratio(&self) -> f64 { self.ratio }
+
+    // cutoff of the kernel, relative to the input rate, and half-width of the
+    // kernel in input samples.
+    fn kernel_scale(&self) -> (f64, f64) {
+        let scale = self.ratio.min(1.0);
+        (scale, HALF_LEN as f64 / scale)
+    }
+
+    /// Latency in input samples, i.e. how many samples have to be pushed
+    /// after an input sample before the output at the same time can be popped.
+    pub fn latency(&self) -> f64 { self.kernel_scale().1 }
+
+    /// Feeds one input sample.
+    pub fn push(&mut self, input: f64) {
+        self.hist.push(input);
+        self.time -= 1.0;
+    }
+
+    /// Pulls the next output sample, if enough input has been pushed.
+    pub fn pop(&mut self) -> Option<f64> {
+        let (scale, half_width) = self.kernel_scale();
+        if self.time + half_width > 0.0 {
+            return None;
+        }
+
+        // input samples within the kernel, as offsets from the newest one
+        let first = (-self.time - half_width).ceil().max(0.0) as usize;
+        let last = (-self.time + half_width).floor() as usize;
+        let step = scale * PHASES as f64;
+        let mut accum = 0.0;
+        for offs in first..=last.min(HIST_LEN - 1) {
+            let pos = (offs as f64 + self.time).abs() * step;
+            let i = pos as usize;
+            let frac = pos - i as f64;
+            let k = self.table[i] + (self.table[i + 1] - self.table[i]) * frac;
+            accum += self.hist[offs] * k;
+        }
+
+        self.time += 1.0 / self.ratio;
+        Some(accum * scale)
+    }
+}
+
+
+/// Runs a process at a fixed internal sample rate, converting the input to the
+/// internal rate and the output back to the host rate.
+///
+/// The wrapped process is accessible through the `process` field, it always
+/// runs at the rate given to `new()`, so its sample rate never needs to change.
+///
+/// # Caveats
+/// Has a latency of about 65 samples when the internal rate is higher than the
+/// host rate, and more when it is lower, see `latency()`. The output is silent
+/// for the latency after creation and after `set_sr()`.
+///
+/// # Examples
+/// Running a diffuser designed for 44.1kHz in a 96kHz host:
+/// ```
+/// use dsp_lab::core::resampling::FixedRate;
+/// use dsp_lab::core::reverb::DenseFirDiffuser;
+/// use dsp_lab::traits::Process;
+/// let mut diffuser = FixedRate::new(DenseFirDiffuser::new(), 44100.0);
+/// diffuser.set_sr(96000.0);
+/// diffuser.process.size = 0.3;
+/// let y = diffuser.step(1.0);
+/// ```
+pub struct FixedRate<P: Process<f64>> {
+    to_internal: Resampler,
+    to_host: Resampler,
+    fifo: VecDeque<f64>,
+    internal_sr: f64,
+    latency: usize,
+    // host samples left before the output starts
+    countdown: usize,
+    pub process: P,
+}
+
+impl<P: Process<f64>> FixedRate<P> {
+    /// Wraps `process`, which will run at `internal_sr`. The host sample rate
+    /// defaults to 44100Hz.
+    pub fn new(process: P, internal_sr: f64) -> Self {
+        let mut ret = Self {
+            to_internal: Resampler::new(),
+            to_host: Resampler::new(),
+            fifo: VecDeque::new(),
+            internal_sr,
+            latency: 0,
+            countdown: 0,
+            process,
+        };
+        ret.set_sr(44100.0);
+        ret
+    }
+
+    /// Sets the host sample rate, clears the state of the conversion.
+    pub fn set_sr(&mut self, sr: f64) {
+        self.to_internal = Resampler::new();
+        self.to_internal.set_rates(sr, self.internal_sr);
+        self.to_host = Resampler::new();
+        self.to_host.set_rates(self.internal_sr, sr);
+
+        // an output is ready when both kernels have enough input, plus one
+        // sample of rounding at the internal rate and one at the host rate
+        let host_per_internal = sr / self.internal_sr;
+        let latency = (2 * HALF_LEN + 1) as f64 * host_per_internal.max(1.0);
+        self.latency = latency.ceil() as usize + 1;
+        self.countdown = self.latency;
+        self.fifo = VecDeque::with_capacity(2 * MAX_RATIO as usize + 2);
+    }
+
+    /// Latency in samples at the host rate, not including the latency of the
+    /// wrapped process itself.
+    pub fn latency(&self) -> usize { self.latency }
+}
+
+impl<P: Process<f64>> Process<f64> for FixedRate<P> {
+    fn step(&mut self, input: f64) -> f64 {
+        self.to_internal.push(input);
+        while let Some(x) = self.to_internal.pop() {
+            self.to_host.push(self.process.step(x));
+            while let Some(y) = self.to_host.pop() {
+                self.fifo.push_back(y);
+            }
+        }
+
+        if self.countdown > 0 {
+            self.countdown -= 1;
+            0.0
+        } else {
+            self.fifo.pop_front().unwrap_or(0.0)
+        }
+    }
+}
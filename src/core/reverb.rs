@@ -19,14 +19,17 @@
 //!   in shroeder reverberators)
 //! - Parallel LBCF reverberators (variation on parallel combs, used in the Freeverb
 //!   algorithm)
+//! - Comb filters: feedforward, feedback and lowpass-feedback (LBCF) combs, the
+//!   building blocks of the parallel comb reverberators
 //! - Spectral diffusers (FFT-based convolution diffusers, are very modern and
 //!   flexible, but also quite CPU intensive)
 //! - FDN reverberators: use linear algebra magic to implement very dense exponentially
 //!   decaying diffusion with low CPU usage. Used extensively by Valhalla DSP reverbs.
 //! - Multiband FDN reverberators: FDN reverberators with different decay times
 //!   for each frequency band, achieved with different amounts of feedback.
-//! **NOTE 1:** for convolution reverbs, use the convolution primitive in the `lin_filter`
-//! module.
+//! 
+//! **NOTE 1:** for convolution reverbs, use the convolution primitives in the
+//! `lin_filter::convolution` module.
 //! 
 //! **NOTE 2:** simple delays are in the `delay` module, or alternatively can be
 //! manually implemented using raw ring buffers from the `core` module.
@@ -46,7 +49,8 @@ use crate::traits::Process;
 use crate::core::RawRingBuffer;
 use crate::core::reverb::tuning::{PRIMES, HO_PRIMES, SPARSE_A, SPARSE_B, SPARSE_C, 
     SPARSE_D, SPARSE_E, SPARSE_F, SPARSE_G, SPARSE_H};
-use crate::shared_enums::{Polarization, ScaleMethod};
+use crate::shared_enums::{Polarization, ScaleMethod, InterpMethod};
+use crate::utils::math;
 
 pub enum TuningVectors {
     A,
@@ -65,7 +69,7 @@ pub enum TuningVectors {
 /// # Caveats
 /// This is designed to work on a fixed sample rate of 44100 Hz. It will work
 /// on other sample rates, but it will sound different. It is suggested that
-/// you downsample before using this.
+/// you run it at 44100 Hz with `core::resampling::FixedRate`.
 /// 
 /// It is also very CPU intensive on `opt-level=0`, but in `opt-level=3` it is
 /// instead extremely efficient.
@@ -289,6 +293,191 @@ impl StereoFirDiffuser {
 }
 
 
+// capacity of the comb filter buffers, a bit less than 3 seconds at 44.1kHz
+const COMB_SIZE: usize = 131072;
+
+// reads a fractional offset from a comb filter buffer, where an offset of 0 is
+// the newest sample.
+fn read_frac(buff: &RawRingBuffer<COMB_SIZE>, offset: f64, interp: &InterpMethod) -> f64 {
+    let offset = offset.clamp(0.0, (COMB_SIZE - 2) as f64);
+    let i = offset.floor() as usize;
+    let x = offset - i as f64;
+    match interp {
+        InterpMethod::Truncate => buff[i],
+        InterpMethod::NearestNeighbor => buff[offset.round() as usize],
+        InterpMethod::Linear => math::x_fade(buff[i], x, buff[i + 1]),
+        // needs one sample on each side, falls back to linear on the newest sample
+        InterpMethod::Quadratic if i == 0 => math::x_fade(buff[0], x, buff[1]),
+        InterpMethod::Quadratic => math::quad_interp(buff[i - 1], buff[i], buff[i + 1], x),
+    }
+}
+
+/// Feedforward comb filter, adds a delayed copy of the input to itself:
+/// `y[n] = x[n] + gain * lp(x[n - delay])`
+/// 
+/// Positive gains put notches at odd multiples of `500 / delay` Hz (delay in
+/// ms), negative gains at even multiples. `damping` low-passes the delayed copy,
+/// between 0 (no damping) and 1 (delayed copy fully removed), so that the notches
+/// become shallower at high frequencies.
+/// 
+/// # Caveats
+/// The delay is clamped between 0 and a bit less than 3 seconds at 44.1kHz
+/// (131072 samples), so the maximum delay in ms shrinks at higher sample rates.
+pub struct CombFF {
+    buff: RawRingBuffer<COMB_SIZE>,
+    lp: f64,
+    sr: f64,
+    pub delay: f64,
+    pub gain: f64,
+    pub damping: f64,
+    pub interp_mode: InterpMethod,
+}
+
+impl CombFF {
+    pub fn new() -> Self {
+        Self {
+            buff: RawRingBuffer::new(),
+            lp: 0.0,
+            sr: 44100.0,
+            delay: 10.0,
+            gain: 0.5,
+            damping: 0.0,
+            interp_mode: InterpMethod::Linear,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) { self.sr = sr; }
+}
+
+impl Process<f64> for CombFF {
+    fn step(&mut self, input: f64) -> f64 {
+        self.buff.push(input);
+        let delayed = read_frac(&self.buff, self.delay * 0.001 * self.sr, &self.interp_mode);
+        let d = self.damping.clamp(0.0, 1.0);
+        self.lp = delayed * (1.0 - d) + self.lp * d;
+        input + self.gain * self.lp
+    }
+}
+
+/// Feedback comb filter, feeds a delayed copy of the output back to the input:
+/// `y[n] = x[n] + feedback * lp(y[n - delay])`
+/// 
+/// Produces resonant peaks at multiples of `1000 / delay` Hz (delay in ms), for
+/// positive feedback, or at odd multiples of `500 / delay` Hz for negative
+/// feedback. `damping` low-passes the feedback path, between 0 (no damping) and
+/// 1 (no feedback), so that high frequencies decay faster, like in real rooms.
+/// 
+/// # Caveats
+/// Feedback is clamped between -0.999 and 0.999 to keep the filter stable. The
+/// delay is clamped between one sample and a bit less than 3 seconds at 44.1kHz.
+pub struct CombFB {
+    buff: RawRingBuffer<COMB_SIZE>,
+    lp: f64,
+    sr: f64,
+    pub delay: f64,
+    pub feedback: f64,
+    pub damping: f64,
+    pub interp_mode: InterpMethod,
+}
+
+impl CombFB {
+    pub fn new() -> Self {
+        Self {
+            buff: RawRingBuffer::new(),
+            lp: 0.0,
+            sr: 44100.0,
+            delay: 10.0,
+            feedback: 0.5,
+            damping: 0.0,
+            interp_mode: InterpMethod::Linear,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) { self.sr = sr; }
+
+    /// Sets the feedback so that the comb decays by 60dB in `t60` milliseconds,
+    /// for the current delay, ignoring damping.
+    pub fn set_decay(&mut self, t60: f64) {
+        self.feedback = comb_decay_to_feedback(self.delay, t60);
+    }
+}
+
+impl Process<f64> for CombFB {
+    fn step(&mut self, input: f64) -> f64 {
+        // the newest sample in the buffer is already one sample old
+        let offset = (self.delay * 0.001 * self.sr - 1.0).max(0.0);
+        let delayed = read_frac(&self.buff, offset, &self.interp_mode);
+        let d = self.damping.clamp(0.0, 1.0);
+        self.lp = delayed * (1.0 - d) + self.lp * d;
+        let output = input + self.feedback.clamp(-0.999, 0.999) * self.lp;
+        self.buff.push(output);
+        output
+    }
+}
+
+/// Lowpass-feedback comb filter (LBCF), the building block of the Freeverb
+/// algorithm, where several of these are run in parallel with slightly different
+/// delays.
+/// 
+/// Unlike `CombFB` the output only contains the delayed signal, without the
+/// dry input, so that parallel LBCFs can be summed directly. The low-pass in the
+/// feedback path is controlled by `damping`, between 0 (no damping) and 1 (no
+/// feedback).
+/// 
+/// # Caveats
+/// Feedback is clamped between -0.999 and 0.999 to keep the filter stable. The
+/// delay is clamped between one sample and a bit less than 3 seconds at 44.1kHz.
+pub struct CombLBCF {
+    buff: RawRingBuffer<COMB_SIZE>,
+    lp: f64,
+    sr: f64,
+    pub delay: f64,
+    pub feedback: f64,
+    pub damping: f64,
+    pub interp_mode: InterpMethod,
+}
+
+impl CombLBCF {
+    /// Creates a new LBCF, with the tuning of the first Freeverb comb.
+    pub fn new() -> Self {
+        Self {
+            buff: RawRingBuffer::new(),
+            lp: 0.0,
+            sr: 44100.0,
+            delay: 1116.0 / 44.1,
+            feedback: 0.84,
+            damping: 0.2,
+            interp_mode: InterpMethod::Linear,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) { self.sr = sr; }
+
+    /// Sets the feedback so that the comb decays by 60dB in `t60` milliseconds,
+    /// for the current delay, ignoring damping.
+    pub fn set_decay(&mut self, t60: f64) {
+        self.feedback = comb_decay_to_feedback(self.delay, t60);
+    }
+}
+
+impl Process<f64> for CombLBCF {
+    fn step(&mut self, input: f64) -> f64 {
+        let offset = (self.delay * 0.001 * self.sr - 1.0).max(0.0);
+        let output = read_frac(&self.buff, offset, &self.interp_mode);
+        let d = self.damping.clamp(0.0, 1.0);
+        self.lp = output * (1.0 - d) + self.lp * d;
+        self.buff.push(input + self.feedback.clamp(-0.999, 0.999) * self.lp);
+        output
+    }
+}
+
+// feedback gain for which a recirculating delay of `delay` ms decays by 60dB in
+// `t60` ms.
+fn comb_decay_to_feedback(delay: f64, t60: f64) -> f64 {
+    if t60 <= 0.0 { return 0.0; }
+    10.0_f64.powf(-3.0 * delay / t60)
+}
+
 
 
 
@@ -0,0 +1,361 @@
+//! Waveshapers, with and without antiderivative anti-aliasing (ADAA).
+//!
+//! Applying a curve sample by sample, like the functions in `utils::math` do,
+//! creates harmonics above nyquist, which fold back as inharmonic aliasing.
+//! ADAA replaces the curve with the average of the curve over the straight line
+//! between consecutive samples, computed from its antiderivatives. This acts as
+//! a low-pass on the generated harmonics, greatly reducing aliasing without
+//! oversampling.
+//!
+//! Curves are described by the `AdaaCurve` trait, which provides the curve and
+//! its first two antiderivatives, and are run by the `Adaa1` (first order) and
+//! `Adaa2` (second order) processes. Curves without a closed form antiderivative
+//! can use numerical integration.
+//!
+//! The `Waveshaper` process runs the curves of `utils::clipping` and
+//! `utils::saturation` directly, without anti-aliasing.
+//!
+//! # Caveats
+//! ADAA also low-passes the signal, the -3dB point is at about a quarter of the
+//! sample rate for first order, and at about a sixth for second order. It also
+//! adds half a sample and a sample of latency respectively. Combining ADAA with
+//! 2x oversampling makes the low-pass inaudible.
+
+use crate::traits::Process;
+use crate::core::lin_filter::DcBlock;
+use crate::utils::math::var_clip;
+use crate::utils::{clipping, saturation};
+
+// below this distance between inputs, the divided differences of ADAA are
+// ill-conditioned and replaced by evaluating the curve at the midpoint.
+const ADAA_EPS: f64 = 1e-5;
+
+/// A waveshaping curve, together with its first and second antiderivatives, as
+/// needed for antiderivative anti-aliasing. The antiderivatives can have any
+/// integration constants.
+pub trait AdaaCurve {
+    /// The curve itself.
+    fn f(&self, x: f64) -> f64;
+
+    /// First antiderivative of the curve.
+    fn f1(&self, x: f64) -> f64;
+
+    /// Second antiderivative of the curve, only needed by `Adaa2`.
+    fn f2(&self, x: f64) -> f64;
+}
+
+
+// 8-point Gauss-Legendre quadrature on [-1, 1], as (node, weight)
+const GAUSS_LEGENDRE_8: [(f64, f64); 8] = [
+    (-0.9602898564975363, 0.1012285362903763),
+    (-0.7966664774136267, 0.2223810344533745),
+    (-0.525532409916329, 0.3137066458778873),
+    (-0.1834346424956498, 0.362683783378362),
+    ( 0.1834346424956498, 0.362683783378362),
+    ( 0.525532409916329, 0.3137066458778873),
+    ( 0.7966664774136267, 0.2223810344533745),
+    ( 0.9602898564975363, 0.1012285362903763),
+];
+
+// First and second antiderivative of an odd curve `f`, by numerical integration
+// from 0 to x. The interval is split at 1, where clipping curves have their
+// knee, and then in octaves, so that each part is smooth and the integration
+// stays accurate for large inputs. The quadrature nodes scale smoothly with x,
+// so the result is a smooth function of x, which is what ADAA needs, and the
+// accuracy is high enough for the ill-conditioned cases of `Adaa2`.
+fn odd_antiderivatives(f: impl Fn(f64) -> f64, x: f64) -> (f64, f64) {
+    let a = x.abs();
+    let mut f1 = 0.0;
+    let mut f2 = 0.0;
+    let mut segment = |lo: f64, hi: f64| {
+        let mid = 0.5 * (lo + hi);
+        let half = 0.5 * (hi - lo);
+        for (node, weight) in GAUSS_LEGENDRE_8.iter() {
+            let t = mid + half * node;
+            let ft = f(t) * weight * half;
+            f1 += ft;
+            f2 += (a - t) * ft;
+        }
+    };
+    segment(0.0, a.min(1.0));
+    let mut lo = 1.0;
+    while a > lo {
+        segment(lo, a.min(2.0 * lo));
+        lo *= 2.0;
+    }
+
+    // the first antiderivative of an odd curve is even, the second is odd
+    (f1, f2.copysign(x))
+}
+
+
+/// `utils::math::var_clip` as an ADAA curve. The antiderivatives have no closed
+/// form, so they are computed by numerical integration, which makes this the
+/// most expensive of the curves.
+///
+/// `hardness` is clamped between 0 and 0.99, as the hard-clip limit can't be
+/// integrated this way.
+pub struct VarClip {
+    pub hardness: f64,
+}
+
+impl VarClip {
+    pub fn new() -> Self { Self { hardness: 0.5 } }
+}
+
+impl AdaaCurve for VarClip {
+    fn f(&self, x: f64) -> f64 { var_clip(x, self.hardness.clamp(0.0, 0.99)) }
+
+    fn f1(&self, x: f64) -> f64 {
+        let h = self.hardness.clamp(0.0, 0.99);
+        odd_antiderivatives(|t| var_clip(t, h), x).0
+    }
+
+    fn f2(&self, x: f64) -> f64 {
+        let h = self.hardness.clamp(0.0, 0.99);
+        odd_antiderivatives(|t| var_clip(t, h), x).1
+    }
+}
+
+
+/// `utils::math::fast_sigmoid` as an ADAA curve, `x / sqrt(1 + x^2)`.
+///
+/// # Caveats
+/// The antiderivatives are exact for the standard implementation of
+/// `fast_sigmoid`, with the `no_fpu` feature the curve is slightly off and the
+/// mismatch adds a tiny amount of distortion.
+pub struct FastSigmoid {}
+
+impl FastSigmoid {
+    pub fn new() -> Self { Self {} }
+}
+
+impl AdaaCurve for FastSigmoid {
+    fn f(&self, x: f64) -> f64 { x / (1.0 + x * x).sqrt() }
+
+    fn f1(&self, x: f64) -> f64 { (1.0 + x * x).sqrt() }
+
+    fn f2(&self, x: f64) -> f64 { 0.5 * (x * (1.0 + x * x).sqrt() + x.asinh()) }
+}
+
+
+/// `utils::math::unbounded_sat` as an ADAA curve, `asinh(x)`.
+pub struct UnboundedSat {}
+
+impl UnboundedSat {
+    pub fn new() -> Self { Self {} }
+}
+
+impl AdaaCurve for UnboundedSat {
+    fn f(&self, x: f64) -> f64 { x.asinh() }
+
+    fn f1(&self, x: f64) -> f64 { x * x.asinh() - (1.0 + x * x).sqrt() }
+
+    fn f2(&self, x: f64) -> f64 {
+        0.25 * (2.0 * x * x - 1.0) * x.asinh() - 0.75 * x * (1.0 + x * x).sqrt()
+    }
+}
+
+
+/// First order ADAA waveshaper, outputs the average of the curve between the
+/// previous and the current input.
+///
+/// # Caveats
+/// Has a latency of half a sample.
+///
+/// # Examples
+/// ```
+/// use dsp_lab::core::waveshapers::{Adaa1, VarClip};
+/// use dsp_lab::traits::Process;
+/// let mut clip = Adaa1::new(VarClip::new());
+/// clip.curve.hardness = 0.8;
+/// let y = clip.step(2.0);
+/// ```
+pub struct Adaa1<C: AdaaCurve> {
+    x_z1: f64,
+    f1_z1: f64,
+    pub curve: C,
+}
+
+impl<C: AdaaCurve> Adaa1<C> {
+    pub fn new(curve: C) -> Self {
+        Self {
+            x_z1: 0.0,
+            f1_z1: curve.f1(0.0),
+            curve,
+        }
+    }
+}
+
+impl<C: AdaaCurve> Process<f64> for Adaa1<C> {
+    fn step(&mut self, input: f64) -> f64 {
+        let f1 = self.curve.f1(input);
+        let dx = input - self.x_z1;
+        let ret = if dx.abs() > ADAA_EPS {
+            (f1 - self.f1_z1) / dx
+        } else {
+            self.curve.f(0.5 * (input + self.x_z1))
+        };
+        self.x_z1 = input;
+        self.f1_z1 = f1;
+        ret
+    }
+}
+
+
+/// Second order ADAA waveshaper, stronger aliasing suppression than `Adaa1`,
+/// at the cost of more computation and a softer top end.
+///
+/// # Caveats
+/// Has a latency of one sample.
+///
+/// # Examples
+/// ```
+/// use dsp_lab::core::waveshapers::{Adaa2, UnboundedSat};
+/// use dsp_lab::traits::Process;
+/// let mut sat = Adaa2::new(UnboundedSat::new());
+/// let y = sat.step(10.0);
+/// ```
+pub struct Adaa2<C: AdaaCurve> {
+    x_z1: f64,
+    x_z2: f64,
+    f2_z1: f64,
+    // divided difference of the second antiderivative between the last two inputs
+    d_z1: f64,
+    pub curve: C,
+}
+
+impl<C: AdaaCurve> Adaa2<C> {
+    pub fn new(curve: C) -> Self {
+        Self {
+            x_z1: 0.0,
+            x_z2: 0.0,
+            f2_z1: curve.f2(0.0),
+            d_z1: curve.f1(0.0),
+            curve,
+        }
+    }
+}
+
+impl<C: AdaaCurve> Process<f64> for Adaa2<C> {
+    fn step(&mut self, input: f64) -> f64 {
+        let (x0, x1, x2) = (input, self.x_z1, self.x_z2);
+        let f2 = self.curve.f2(x0);
+
+        // first divided difference of the second antiderivative
+        let dx = x0 - x1;
+        let d = if dx.abs() > ADAA_EPS {
+            (f2 - self.f2_z1) / dx
+        } else {
+            self.curve.f1(0.5 * (x0 + x1))
+        };
+
+        let dx2 = x0 - x2;
+        let ret = if dx2.abs() > ADAA_EPS {
+            2.0 * (d - self.d_z1) / dx2
+        } else {
+            // x0 and x2 are close, expand around their midpoint
+            let x_bar = 0.5 * (x0 + x2);
+            let delta = x_bar - x1;
+            if delta.abs() > ADAA_EPS {
+                let f2_bar = self.curve.f2(x_bar);
+                2.0 / delta * (self.curve.f1(x_bar) + (self.f2_z1 - f2_bar) / delta)
+            } else {
+                self.curve.f(0.5 * (x_bar + x1))
+            }
+        };
+
+        self.x_z2 = x1;
+        self.x_z1 = x0;
+        self.f2_z1 = f2;
+        self.d_z1 = d;
+        ret
+    }
+}
+
+
+// === STATIC WAVESHAPERS ===
+
+/// Used to select the curve of a `Waveshaper`, see `utils::clipping` and
+/// `utils::saturation` for a description of each curve.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ShaperCurve {
+    HardClip,
+    CubicClip,
+    Tanh,
+    Atan,
+    Algebraic,
+    Tube,
+    DiodePair,
+    Foldback,
+    SineFold,
+    Exponential,
+}
+
+/// Waveshaper running one of the curves from `utils::clipping` and
+/// `utils::saturation`, with a DC blocker after asymmetric curves.
+///
+/// - `curve`: the waveshaping curve
+/// - `drive`: linear gain before the curve
+/// - `bias`: offset after the drive, makes the curve asymmetric
+///
+/// The curve is asymmetric when it is `Tube`, or when `bias` is not 0, in which
+/// case the DC it produces is removed by a high-pass at 10Hz.
+///
+/// # Caveats
+/// The curves are applied sample by sample, and alias when driven hard. Wrap
+/// the waveshaper in `core::oversampling::Oversampler` for cleaner results.
+///
+/// # Examples
+/// ```
+/// use dsp_lab::core::waveshapers::{Waveshaper, ShaperCurve};
+/// use dsp_lab::traits::Process;
+/// let mut shaper = Waveshaper::new();
+/// shaper.curve = ShaperCurve::Tube;
+/// shaper.drive = 4.0;
+/// let y = shaper.step(0.5);
+/// ```
+pub struct Waveshaper {
+    dc_block: DcBlock,
+    pub curve: ShaperCurve,
+    pub drive: f64,
+    pub bias: f64,
+}
+
+impl Waveshaper {
+    /// Creates a `tanh` waveshaper with a drive of 1 and no bias.
+    pub fn new() -> Self {
+        Self {
+            dc_block: DcBlock::new(),
+            curve: ShaperCurve::Tanh,
+            drive: 1.0,
+            bias: 0.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        self.dc_block.set_sr(sr);
+    }
+}
+
+impl Process<f64> for Waveshaper {
+    fn step(&mut self, input: f64) -> f64 {
+        let (d, b) = (self.drive, self.bias);
+        let y = match self.curve {
+            ShaperCurve::HardClip    => clipping::hard_clip(input, d, b),
+            ShaperCurve::CubicClip   => clipping::cubic_clip(input, d, b),
+            ShaperCurve::Tanh        => clipping::tanh_clip(input, d, b),
+            ShaperCurve::Atan        => clipping::atan_clip(input, d, b),
+            ShaperCurve::Algebraic   => clipping::algebraic_clip(input, d, b),
+            ShaperCurve::Tube        => saturation::tube(input, d, b),
+            ShaperCurve::DiodePair   => saturation::diode_pair(input, d, b),
+            ShaperCurve::Foldback    => saturation::foldback(input, d, b),
+            ShaperCurve::SineFold    => saturation::sine_fold(input, d, b),
+            ShaperCurve::Exponential => saturation::exponential(input, d, b),
+        };
+
+        // the DC blocker always runs, so that switching curves doesn't click
+        let blocked = self.dc_block.step(y);
+        if self.curve == ShaperCurve::Tube || self.bias != 0.0 { blocked } else { y }
+    }
+}
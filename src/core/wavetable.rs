@@ -0,0 +1,196 @@
+//! Wavetable oscillator, with mip-mapped band-limited tables.
+//!
+//! A `Wavetable` is built from samples, either a single cycle of a waveform,
+//! or a sequence of single cycle frames, like the wavetables of most software
+//! synthesizers. Each frame is resampled to a fixed length and band-limited
+//! with an FFT into one table per octave (mip-maps), each with half the
+//! harmonics of the previous one. The oscillator reads from the table with the
+//! most harmonics that don't alias at the current frequency, and crossfades
+//! between neighbouring frames with its `position`.
+//!
+//! Tables are shared through an `Arc`, so that the voices of a synth can play
+//! the same wavetable without copying it.
+//!
+//! # Caveats
+//! Building a table is slow and allocates, do it outside of the audio thread.
+//! Each frame takes 160kB, as every octave is stored at full length.
+//!
+//! Since harmonics are removed one octave at a time, the output is only
+//! band-limited to between half of nyquist and nyquist, depending on where the
+//! frequency falls within the octave, which is inaudible for most frequencies
+//! at 44.1kHz and above.
+
+use std::f64::consts;
+use std::sync::Arc;
+
+use num::complex::Complex;
+use rustfft::FftPlanner;
+
+use crate::traits::Source;
+use crate::shared_enums::InterpMethod;
+use crate::core::osc::RampCore;
+use crate::utils::math::{x_fade, quad_interp};
+
+// length of the tables, must be a power of 2
+const TABLE_LEN: usize = 2048;
+const TABLE_MASK: usize = TABLE_LEN - 1;
+
+// number of octaves, the first table has 1023 harmonics, the last one has only
+// the fundamental
+const LEVELS: usize = 10;
+
+/// Single cycle waveform, or sequence of frames, band-limited for each octave,
+/// to be played by `WavetableOsc`.
+pub struct Wavetable {
+    // tables of each frame, one per octave, the first octave of the first
+    // frame is followed by the second octave of the first frame, and so on
+    tables: Vec<Box<[f64]>>,
+    num_frames: usize,
+}
+
+impl Wavetable {
+    /// Builds a wavetable from a single cycle of a waveform, of any length.
+    ///
+    /// Panics if `cycle` is empty.
+    pub fn from_cycle(cycle: &[f64]) -> Self {
+        Self::from_frames(cycle, cycle.len())
+    }
+
+    /// Builds a wavetable from consecutive single cycle frames, each
+    /// `frame_len` samples long. Leftover samples at the end, which aren't
+    /// enough for a whole frame, are ignored.
+    ///
+    /// Panics if `frame_len` is 0 or longer than `samples`.
+    pub fn from_frames(samples: &[f64], frame_len: usize) -> Self {
+        assert!(frame_len > 0 && frame_len <= samples.len());
+
+        let mut planner = FftPlanner::new();
+        let fft_fwd = planner.plan_fft_forward(frame_len);
+        let fft_bwd = planner.plan_fft_inverse(TABLE_LEN);
+
+        // harmonics above the nyquist of the frame are not available, the
+        // nyquist bin itself is ambiguous, so it is dropped too
+        let frame_harmonics = (frame_len - 1) / 2;
+
+        let num_frames = samples.len() / frame_len;
+        let mut tables = Vec::with_capacity(num_frames * LEVELS);
+        for frame in samples.chunks_exact(frame_len) {
+            let mut spectrum: Vec<Complex<f64>> = frame.iter()
+                .map(|x| Complex::new(*x / frame_len as f64, 0.0))
+                .collect();
+            fft_fwd.process(&mut spectrum);
+
+            for level in 0..LEVELS {
+                let harmonics = ((TABLE_LEN / 2 - 1) >> level).min(frame_harmonics);
+                let mut buf = vec![Complex::new(0.0, 0.0); TABLE_LEN];
+                buf[0] = spectrum[0];
+                for k in 1..=harmonics {
+                    buf[k] = spectrum[k];
+                    buf[TABLE_LEN - k] = spectrum[k].conj();
+                }
+                fft_bwd.process(&mut buf);
+                tables.push(buf.iter().map(|c| c.re).collect());
+            }
+        }
+
+        Self { tables, num_frames }
+    }
+
+    pub fn num_frames(&self) -> usize { self.num_frames }
+
+    // reads the table of `frame` and `level` at `pos`, in samples
+    fn read(&self, frame: usize, level: usize, pos: f64, interp: &InterpMethod) -> f64 {
+        let table = &self.tables[frame * LEVELS + level];
+        let i = pos.floor() as usize & TABLE_MASK;
+        let x = pos - pos.floor();
+        match interp {
+            InterpMethod::Truncate => table[i],
+            InterpMethod::NearestNeighbor => table[pos.round() as usize & TABLE_MASK],
+            InterpMethod::Linear => x_fade(table[i], x, table[(i + 1) & TABLE_MASK]),
+            InterpMethod::Quadratic => quad_interp(table[(i + TABLE_MASK) & TABLE_MASK],
+                table[i], table[(i + 1) & TABLE_MASK], x),
+        }
+    }
+}
+
+
+/// Wavetable oscillator.
+///
+/// - `position`: position within the frames of the wavetable, between 0 (first
+///   frame) and 1 (last frame), neighbouring frames are crossfaded
+/// - `interp_mode`: interpolation between the samples of the tables
+///
+/// The frequency can be modulated at every sample, negative frequencies play
+/// the table backwards.
+///
+/// # Examples
+/// Sweeping through a wavetable that morphs from a sine to a square:
+/// ```
+/// use std::sync::Arc;
+/// use std::f64::consts;
+/// use dsp_lab::core::wavetable::{Wavetable, WavetableOsc};
+/// use dsp_lab::traits::Source;
+/// let mut samples = vec![];
+/// for n in 0..512 {
+///     samples.push((consts::TAU * n as f64 / 512.0).sin());
+/// }
+/// for n in 0..512 {
+///     samples.push(if n < 256 { 1.0 } else { -1.0 });
+/// }
+/// let table = Arc::new(Wavetable::from_frames(&samples, 512));
+/// let mut osc = WavetableOsc::new(table);
+/// osc.set_freq(220.0);
+/// for n in 0..1000 {
+///     osc.position = n as f64 / 1000.0;
+///     let y = osc.step();
+/// }
+/// ```
+pub struct WavetableOsc {
+    osc: RampCore,
+    table: Arc<Wavetable>,
+    pub position: f64,
+    pub interp_mode: InterpMethod,
+}
+
+impl WavetableOsc {
+    /// Creates an oscillator playing the first frame of `table`.
+    pub fn new(table: Arc<Wavetable>) -> Self {
+        Self {
+            osc: RampCore::new(),
+            table,
+            position: 0.0,
+            interp_mode: InterpMethod::Linear,
+        }
+    }
+
+    /// Changes the wavetable, keeping the phase of the oscillator.
+    pub fn set_table(&mut self, table: Arc<Wavetable>) { self.table = table; }
+
+    pub fn set_sr(&mut self, sr: f64) { self.osc.sr = sr; }
+
+    pub fn set_freq(&mut self, freq: f64) { self.osc.set_freq(freq); }
+
+    pub fn set_phase(&mut self, phase: f64) { self.osc.set_phase(phase); }
+}
+
+impl Source<f64> for WavetableOsc {
+    fn step(&mut self) -> f64 {
+        let dt = self.osc.norm_phase_inc().abs();
+        let pos = self.osc.step() / consts::TAU * TABLE_LEN as f64;
+
+        // highest octave whose harmonics stay below nyquist
+        let level = if dt > 0.0 {
+            (dt * TABLE_LEN as f64).log2().ceil().clamp(0.0, (LEVELS - 1) as f64) as usize
+        } else { 0 };
+
+        let frame = self.position.clamp(0.0, 1.0) * (self.table.num_frames - 1) as f64;
+        let i = frame.floor() as usize;
+        let x = frame - i as f64;
+        let a = self.table.read(i, level, pos, &self.interp_mode);
+        if x == 0.0 {
+            a
+        } else {
+            x_fade(a, x, self.table.read(i + 1, level, pos, &self.interp_mode))
+        }
+    }
+}
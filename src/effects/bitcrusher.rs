@@ -0,0 +1,192 @@
+//! Bitcrusher and sample rate reducer, for lo-fi sounds.
+//!
+//! The sample rate is reduced by holding the input for several samples, which
+//! folds the spectrum above the new nyquist back down as inharmonic aliasing,
+//! and the bit depth is reduced by rounding to fewer levels, which adds
+//! quantization noise and, on quiet or simple signals, harsh distortion.
+//!
+//! Both can be tamed for more faithful emulation of low quality converters:
+//! anti-alias filters before and after the rate reduction, and dither with
+//! noise shaping for the bit reduction.
+
+use crate::traits::{Process, Source};
+use crate::core::chaos::NoiseWhite;
+use crate::core::lin_filter::BiquadLowPass;
+
+// Q-factors of a 4th order butterworth, as two biquads
+const BUTTERWORTH_Q: [f64; 2] = [0.5411961001461971, 1.3065629648763766];
+
+// anti-alias filters are tuned slightly below the reduced nyquist
+const FILTER_MARGIN: f64 = 0.45;
+
+/// Used to select the dither added before bit reduction.
+///
+/// - Off: no dither, the quantization error follows the signal, and sounds
+///   like distortion
+/// - Rectangular: uniform noise of one step, removes most distortion
+/// - Triangular: sum of two uniform noises, removes all distortion at the cost
+///   of slightly more noise
+#[derive(Clone, Copy, PartialEq)]
+pub enum Dither {
+    Off,
+    Rectangular,
+    Triangular,
+}
+
+/// Used to select the noise shaping of the bit reduction, which feeds back the
+/// quantization error to move the noise towards high frequencies.
+///
+/// - Off: flat noise
+/// - FirstOrder: noise rises by 6dB per octave
+/// - SecondOrder: noise rises by 12dB per octave, with a quieter low end
+#[derive(Clone, Copy, PartialEq)]
+pub enum NoiseShaping {
+    Off,
+    FirstOrder,
+    SecondOrder,
+}
+
+// 4th order low-pass, made of two biquads
+struct AntiAlias {
+    stages: [BiquadLowPass; 2],
+}
+
+impl AntiAlias {
+    fn new() -> Self {
+        let mut ret = Self { stages: [BiquadLowPass::new(), BiquadLowPass::new()] };
+        for (stage, q) in ret.stages.iter_mut().zip(BUTTERWORTH_Q.iter()) {
+            stage.q = *q;
+        }
+        ret
+    }
+
+    fn set_sr(&mut self, sr: f64) {
+        for stage in self.stages.iter_mut() { stage.set_sr(sr); }
+    }
+
+    fn step(&mut self, input: f64, cutoff: f64) -> f64 {
+        self.stages.iter_mut().fold(input, |x, stage| {
+            stage.cutoff = cutoff;
+            stage.step(x)
+        })
+    }
+}
+
+/// Bit depth and sample rate reducer.
+///
+/// - `bits`: bit depth, between 1 and 32, fractional values give a number of
+///   levels in between
+/// - `rate`: reduced sample rate in hertz, fractional values are fine, at or
+///   above the sample rate there is no rate reduction
+/// - `dither`: dither added before the bit reduction
+/// - `noise_shaping`: noise shaping of the bit reduction
+/// - `pre_filter`: low-pass at the reduced nyquist before the rate reduction,
+///   removes the aliasing
+/// - `post_filter`: low-pass at the reduced nyquist after the rate reduction,
+///   smooths the steps
+///
+/// Dither and noise shaping run at the reduced rate, like they would in a real
+/// low quality converter.
+///
+/// # Examples
+/// ```
+/// use dsp_lab::effects::bitcrusher::{Bitcrusher, Dither};
+/// use dsp_lab::traits::Process;
+/// let mut crusher = Bitcrusher::new(0);
+/// crusher.bits = 8.0;
+/// crusher.rate = 11025.0;
+/// crusher.dither = Dither::Triangular;
+/// let y = crusher.step(0.3);
+/// ```
+pub struct Bitcrusher {
+    noise: NoiseWhite,
+    pre: AntiAlias,
+    post: AntiAlias,
+    // position within the current held sample, wraps at 1
+    phase: f64,
+    held: f64,
+    err_z1: f64,
+    err_z2: f64,
+    sr: f64,
+
+    pub bits: f64,
+    pub rate: f64,
+    pub dither: Dither,
+    pub noise_shaping: NoiseShaping,
+    pub pre_filter: bool,
+    pub post_filter: bool,
+}
+
+impl Bitcrusher {
+    /// Creates a bitcrusher at 8 bits and 11025Hz, without dither and filters,
+    /// `seed` seeds the dither noise.
+    pub fn new(seed: u8) -> Self {
+        let mut ret = Self {
+            noise: NoiseWhite::new(seed),
+            pre: AntiAlias::new(),
+            post: AntiAlias::new(),
+            // so that the first input is sampled right away
+            phase: 1.0,
+            held: 0.0,
+            err_z1: 0.0,
+            err_z2: 0.0,
+            sr: 44100.0,
+
+            bits: 8.0,
+            rate: 11025.0,
+            dither: Dither::Off,
+            noise_shaping: NoiseShaping::Off,
+            pre_filter: false,
+            post_filter: false,
+        };
+        ret.set_sr(44100.0);
+        ret
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        self.sr = sr;
+        self.pre.set_sr(sr);
+        self.post.set_sr(sr);
+    }
+
+    // rounds to the bit depth, with dither and noise shaping
+    fn quantize(&mut self, x: f64) -> f64 {
+        let step = (1.0 - self.bits.clamp(1.0, 32.0)).exp2();
+
+        // subtract the filtered error of previous samples
+        let shaped = x - match self.noise_shaping {
+            NoiseShaping::Off => 0.0,
+            NoiseShaping::FirstOrder => self.err_z1,
+            NoiseShaping::SecondOrder => 2.0 * self.err_z1 - self.err_z2,
+        };
+        let dither = match self.dither {
+            Dither::Off => 0.0,
+            Dither::Rectangular => self.noise.step() - 0.5,
+            Dither::Triangular => self.noise.step() + self.noise.step() - 1.0,
+        };
+        let y = ((shaped / step + dither).round() * step).clamp(-1.0, 1.0);
+
+        self.err_z2 = self.err_z1;
+        self.err_z1 = y - shaped;
+        y
+    }
+}
+
+impl Process<f64> for Bitcrusher {
+    fn step(&mut self, input: f64) -> f64 {
+        let rate = self.rate.clamp(1.0, self.sr);
+        let cutoff = FILTER_MARGIN * rate;
+
+        let x = if self.pre_filter { self.pre.step(input, cutoff) } else { input };
+
+        // sample and hold, the accumulator keeps the fractional part so that
+        // the average rate is exact
+        self.phase += rate / self.sr;
+        if self.phase >= 1.0 {
+            self.phase -= self.phase.floor();
+            self.held = self.quantize(x);
+        }
+
+        if self.post_filter { self.post.step(self.held, cutoff) } else { self.held }
+    }
+}
@@ -0,0 +1,275 @@
+//! Dynamic EQ, where the gain of each band follows the level of the signal in
+//! that band.
+//!
+//! Each band has a sidechain filter that isolates the band from the input (or
+//! from an external key signal), followed by a peak level detector with attack
+//! and release. The detected level drives the gain of a peaking or shelving
+//! filter, like a compressor that only acts on part of the spectrum. This is
+//! useful for de-essing, taming resonances that only ring out on loud notes,
+//! or bringing out detail only when it's there.
+
+use crate::traits::Process;
+use crate::core::lin_filter::{BiquadPeaking, BiquadLowShelf, BiquadHighShelf,
+    BiquadBandPass, BiquadLowPass, BiquadHighPass};
+use crate::utils::conversion::gain_to_db;
+
+// time constant of the fade when a band is enabled or disabled, in milliseconds
+const ENABLE_FADE_MS: f64 = 20.0;
+
+/// Used to select the shape of a dynamic EQ band, and of its sidechain filter.
+///
+/// - Peaking: bell shaped band, the sidechain is a band-pass at the same frequency
+/// - LowShelf: shelf below the band frequency, the sidechain is a low-pass
+/// - HighShelf: shelf above the band frequency, the sidechain is a high-pass
+#[derive(Clone, Copy, PartialEq)]
+pub enum DynBandType {
+    Peaking,
+    LowShelf,
+    HighShelf,
+}
+
+/// Used to select in which direction a dynamic EQ band acts.
+///
+/// - Downward: cuts the band when it exceeds the threshold, like a compressor
+/// - Upward: boosts the band when it exceeds the threshold, like an expander
+#[derive(Clone, Copy, PartialEq)]
+pub enum DynMode {
+    Downward,
+    Upward,
+}
+
+// filters used by a band, kept for all band types so that switching type
+// doesn't allocate.
+struct DynFilters {
+    peaking: BiquadPeaking,
+    low_shelf: BiquadLowShelf,
+    high_shelf: BiquadHighShelf,
+    sc_band_pass: BiquadBandPass,
+    sc_low_pass: BiquadLowPass,
+    sc_high_pass: BiquadHighPass,
+}
+
+/// A single band of `DynamicEq`.
+///
+/// - `band_type`: shape of the band and of the sidechain filter
+/// - `freq`: center or corner frequency in hertz
+/// - `q`: width of the band, for shelves this is the shelf slope
+/// - `threshold`: level in dBFS above which the band starts acting
+/// - `ratio`: how much the gain changes for each dB above the threshold, like in
+///   a compressor, with a ratio of 4 the band cuts 3dB every 4dB above threshold
+/// - `attack`, `release`: time constants of the level detector, in milliseconds
+/// - `range`: maximum amount of gain change in dB
+/// - `mode`: wether the band cuts or boosts above the threshold
+/// - `enabled`: wether the band is active, the gain of the band is faded in and
+///   out when this changes
+pub struct DynamicBand {
+    pub band_type: DynBandType,
+    pub freq: f64,
+    pub q: f64,
+    pub threshold: f64,
+    pub ratio: f64,
+    pub attack: f64,
+    pub release: f64,
+    pub range: f64,
+    pub mode: DynMode,
+    pub enabled: bool,
+
+    filters: DynFilters,
+    env: f64,
+    gain_db: f64,
+    // fades the gain in and out when the band is enabled or disabled
+    mix: f64,
+}
+
+impl DynamicBand {
+    fn new(sr: f64) -> Self {
+        let mut ret = Self {
+            band_type: DynBandType::Peaking,
+            freq: 1000.0,
+            q: 1.0,
+            threshold: -20.0,
+            ratio: 2.0,
+            attack: 5.0,
+            release: 100.0,
+            range: 12.0,
+            mode: DynMode::Downward,
+            enabled: true,
+
+            filters: DynFilters {
+                peaking: BiquadPeaking::new(),
+                low_shelf: BiquadLowShelf::new(),
+                high_shelf: BiquadHighShelf::new(),
+                sc_band_pass: BiquadBandPass::new(),
+                sc_low_pass: BiquadLowPass::new(),
+                sc_high_pass: BiquadHighPass::new(),
+            },
+            env: 0.0,
+            gain_db: 0.0,
+            mix: 1.0,
+        };
+        ret.set_sr(sr);
+        ret
+    }
+
+    fn set_sr(&mut self, sr: f64) {
+        let f = &mut self.filters;
+        f.peaking.set_sr(sr);
+        f.low_shelf.set_sr(sr);
+        f.high_shelf.set_sr(sr);
+        f.sc_band_pass.set_sr(sr);
+        f.sc_low_pass.set_sr(sr);
+        f.sc_high_pass.set_sr(sr);
+    }
+
+    /// Current gain of the band in dB, for metering.
+    pub fn gain_db(&self) -> f64 { self.gain_db }
+
+    fn step(&mut self, input: f64, sidechain: f64, sr: f64) -> f64 {
+        let f = &mut self.filters;
+
+        // band-filtered sidechain
+        let sc = match self.band_type {
+            DynBandType::Peaking => {
+                f.sc_band_pass.cutoff = self.freq;
+                f.sc_band_pass.q = self.q;
+                f.sc_band_pass.step(sidechain)
+            },
+            DynBandType::LowShelf => {
+                f.sc_low_pass.cutoff = self.freq;
+                f.sc_low_pass.step(sidechain)
+            },
+            DynBandType::HighShelf => {
+                f.sc_high_pass.cutoff = self.freq;
+                f.sc_high_pass.step(sidechain)
+            },
+        };
+
+        // peak level detector, with separate attack and release
+        let level = sc.abs();
+        let time_ms = if level > self.env { self.attack } else { self.release };
+        let coeff = (-1.0 / (time_ms.max(0.01) * 0.001 * sr)).exp();
+        self.env = level + (self.env - level) * coeff;
+
+        // static gain curve
+        let over = (gain_to_db(self.env.max(1e-30)) - self.threshold).max(0.0);
+        let amount = (over * (1.0 - 1.0 / self.ratio.max(1.0))).min(self.range.abs());
+        let mix_target = if self.enabled { 1.0 } else { 0.0 };
+        let fade = (-1.0 / (ENABLE_FADE_MS * 0.001 * sr)).exp();
+        self.mix = mix_target + (self.mix - mix_target) * fade;
+        self.gain_db = self.mix * match self.mode {
+            DynMode::Downward => -amount,
+            DynMode::Upward   =>  amount,
+        };
+
+        // the peaking and shelf biquads apply twice their gain in decibels
+        // the filter keeps running when disabled, and the gain fades, so
+        // toggling the band doesn't click
+        match self.band_type {
+            DynBandType::Peaking => {
+                f.peaking.cutoff = self.freq;
+                f.peaking.q = self.q;
+                f.peaking.db_gain = 0.5 * self.gain_db;
+                f.peaking.step(input)
+            },
+            DynBandType::LowShelf => {
+                f.low_shelf.cutoff = self.freq;
+                f.low_shelf.q = self.q;
+                f.low_shelf.db_gain = 0.5 * self.gain_db;
+                f.low_shelf.step(input)
+            },
+            DynBandType::HighShelf => {
+                f.high_shelf.cutoff = self.freq;
+                f.high_shelf.q = self.q;
+                f.high_shelf.db_gain = 0.5 * self.gain_db;
+                f.high_shelf.step(input)
+            },
+        }
+    }
+}
+
+
+/// Dynamic EQ with a configurable number of bands, processed in series.
+///
+/// When used as a `Process`, each band detects the level of the input itself,
+/// use `step_with_sidechain()` to drive the bands from an external key signal.
+///
+/// # Examples
+/// De-esser:
+/// ```
+/// use dsp_lab::effects::dynamic_eq::{DynamicEq, DynBandType};
+/// let mut de_esser = DynamicEq::new(1);
+/// if let Some(band) = de_esser.band(0) {
+///     band.band_type = DynBandType::HighShelf;
+///     band.freq = 5000.0;
+///     band.threshold = -30.0;
+///     band.ratio = 4.0;
+/// }
+/// ```
+pub struct DynamicEq {
+    bands: Vec<DynamicBand>,
+    sr: f64,
+}
+
+impl DynamicEq {
+    /// Creates a dynamic EQ with `num_bands` peaking bands at 1kHz.
+    pub fn new(num_bands: usize) -> Self {
+        Self {
+            bands: (0..num_bands).map(|_| DynamicBand::new(44100.0)).collect(),
+            sr: 44100.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        self.sr = sr;
+        for band in self.bands.iter_mut() {
+            band.set_sr(sr);
+        }
+    }
+
+    /// Number of bands in the EQ.
+    pub fn num_bands(&self) -> usize { self.bands.len() }
+
+    /// Adds a new peaking band at 1kHz.
+    /// # Returns
+    /// - index of the new band
+    pub fn add_band(&mut self) -> usize {
+        self.bands.push(DynamicBand::new(self.sr));
+        self.bands.len() - 1
+    }
+
+    /// Removes a band.
+    /// # Returns
+    /// - boolean representing wether the band existed in the first place.
+    /// # Side-effects
+    /// The vector of bands is shifted, thus all indexes greater than the one
+    /// removed are shifted with it.
+    pub fn remove_band(&mut self, index: usize) -> bool {
+        if index < self.bands.len() {
+            self.bands.remove(index);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Gives access to the parameters of a band, or `None` if it doesn't exist.
+    pub fn band(&mut self, index: usize) -> Option<&mut DynamicBand> {
+        self.bands.get_mut(index)
+    }
+
+    /// Processes `input`, with the level detection of every band driven by
+    /// `sidechain` instead of the input.
+    pub fn step_with_sidechain(&mut self, input: f64, sidechain: f64) -> f64 {
+        let sr = self.sr;
+        self.bands.iter_mut().fold(input, |x, band| band.step(x, sidechain, sr))
+    }
+}
+
+impl Process<f64> for DynamicEq {
+    fn step(&mut self, input: f64) -> f64 {
+        // each band detects the output of the previous band, so that bands
+        // don't react to what was already removed.
+        let sr = self.sr;
+        self.bands.iter_mut().fold(input, |x, band| band.step(x, x, sr))
+    }
+}
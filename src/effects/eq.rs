@@ -0,0 +1,384 @@
+//! Multi-band parametric EQ, built from the biquad filters in `core::lin_filter`.
+//!
+//! Each band can be a peaking filter, a shelf or a low/high cut, and bands can
+//! be added, removed and reconfigured while running. All parameter changes are
+//! smoothed, and changes that would make the filter jump (like changing the
+//! type of a band) are crossfaded, so nothing clicks.
+//!
+//! The combined frequency response of all bands can be queried, e.g. for drawing
+//! the EQ curve in a user interface.
+
+use std::f64::consts;
+
+use crate::traits::Process;
+use crate::core::lin_filter::{BiquadPeaking, BiquadLowShelf, BiquadHighShelf,
+    BiquadLowPass, BiquadHighPass};
+use crate::utils::conversion::f_to_omega;
+use crate::utils::math::c_mul;
+
+// time constant of parameter smoothing and crossfades, in milliseconds
+const SMOOTHING_MS: f64 = 20.0;
+
+// maximum number of 2-pole sections of low and high pass bands, for 96dB/oct
+const MAX_SECTIONS: usize = 8;
+
+/// Used to select the shape of an EQ band
+///
+/// - Peaking: bell shaped boost or cut around the band frequency
+/// - LowShelf: boost or cut everything below the band frequency
+/// - HighShelf: boost or cut everything above the band frequency
+/// - LowPass: cut everything above the band frequency, with variable slope
+/// - HighPass: cut everything below the band frequency, with variable slope
+#[derive(Clone, Copy, PartialEq)]
+pub enum BandType {
+    Peaking,
+    LowShelf,
+    HighShelf,
+    LowPass,
+    HighPass,
+}
+
+/// A single band of `ParametricEq`.
+///
+/// - `band_type`: shape of the band
+/// - `freq`: center or corner frequency in hertz
+/// - `db_gain`: gain in decibels, ignored by low and high pass bands
+/// - `q`: width of the band, for shelves this is the shelf slope, for low and
+///   high pass it is the resonance (0.707 is flat)
+/// - `slope`: slope of low and high pass bands in dB/oct, rounded to a multiple
+///   of 12dB/oct, up to 96dB/oct
+/// - `enabled`: wether the band is active, the band is faded in and out when
+///   this changes
+pub struct EqBand {
+    pub band_type: BandType,
+    pub freq: f64,
+    pub db_gain: f64,
+    pub q: f64,
+    pub slope: f64,
+    pub enabled: bool,
+
+    // filters of every type, only the one of `active_type` runs, low and high
+    // pass filters are made of `active_sections` sections in series for
+    // steeper slopes. They are all allocated upfront, so that changing the
+    // type doesn't allocate on the audio thread.
+    peaking: BiquadPeaking,
+    low_shelf: BiquadLowShelf,
+    high_shelf: BiquadHighShelf,
+    low_pass: [BiquadLowPass; MAX_SECTIONS],
+    high_pass: [BiquadHighPass; MAX_SECTIONS],
+    active_type: BandType,
+    active_sections: usize,
+
+    // smoothed parameters, frequency is smoothed in the log domain
+    log_freq: f64,
+    gain_smooth: f64,
+    q_smooth: f64,
+    mix: f64,
+}
+
+impl EqBand {
+    fn new(freq: f64, sr: f64) -> Self {
+        let mut ret = Self {
+            band_type: BandType::Peaking,
+            freq,
+            db_gain: 0.0,
+            q: 0.707,
+            slope: 12.0,
+            enabled: true,
+
+            peaking: BiquadPeaking::new(),
+            low_shelf: BiquadLowShelf::new(),
+            high_shelf: BiquadHighShelf::new(),
+            low_pass: std::array::from_fn(|_| BiquadLowPass::new()),
+            high_pass: std::array::from_fn(|_| BiquadHighPass::new()),
+            active_type: BandType::Peaking,
+            active_sections: 1,
+
+            log_freq: freq.max(1.0).log2(),
+            gain_smooth: 0.0,
+            q_smooth: 0.707,
+            mix: 1.0,
+        };
+        ret.rebuild(sr);
+        ret
+    }
+
+    // number of 2-pole sections needed for the current type and slope
+    fn sections(&self) -> usize {
+        match self.band_type {
+            BandType::LowPass | BandType::HighPass =>
+                ((self.slope / 12.0).round() as usize).clamp(1, MAX_SECTIONS),
+            _ => 1,
+        }
+    }
+
+    // Q of each section of a butterworth filter made of `n` sections, scaled
+    // so that the resonance is set by `q`
+    fn section_q(q: f64, section: usize, n: usize) -> f64 {
+        let angle = consts::PI * (2 * section + 1) as f64 / (4 * n) as f64;
+        q * consts::SQRT_2 / (2.0 * angle.cos())
+    }
+
+    // switch to the filter matching the current settings, with cleared state,
+    // and jump the smoothed parameters to their targets
+    fn rebuild(&mut self, sr: f64) {
+        let n = self.sections();
+        match self.band_type {
+            BandType::Peaking   => self.peaking = BiquadPeaking::new(),
+            BandType::LowShelf  => self.low_shelf = BiquadLowShelf::new(),
+            BandType::HighShelf => self.high_shelf = BiquadHighShelf::new(),
+            BandType::LowPass   => for f in self.low_pass.iter_mut().take(n) { *f = BiquadLowPass::new() },
+            BandType::HighPass  => for f in self.high_pass.iter_mut().take(n) { *f = BiquadHighPass::new() },
+        }
+        self.active_type = self.band_type;
+        self.active_sections = n;
+        self.log_freq = self.freq.max(1.0).log2();
+        self.gain_smooth = self.db_gain;
+        self.q_smooth = self.q;
+        self.set_sr(sr);
+    }
+
+    fn set_sr(&mut self, sr: f64) {
+        self.peaking.set_sr(sr);
+        self.low_shelf.set_sr(sr);
+        self.high_shelf.set_sr(sr);
+        for f in self.low_pass.iter_mut() { f.set_sr(sr) }
+        for f in self.high_pass.iter_mut() { f.set_sr(sr) }
+    }
+
+    fn step(&mut self, input: f64, smooth: f64, sr: f64) -> f64 {
+        // fade out before switching to a different filter, then fade back in
+        let matches = self.band_type == self.active_type
+                   && self.sections() == self.active_sections;
+        let mix_target = if self.enabled && matches { 1.0 } else { 0.0 };
+        self.mix = mix_target + (self.mix - mix_target) * smooth;
+        if !matches && self.mix < 1e-4 {
+            self.rebuild(sr);
+            self.mix = 0.0;
+        }
+        if mix_target == 0.0 && self.mix < 1e-6 {
+            self.mix = 0.0;
+            return input;
+        }
+
+        // smooth parameters
+        let log_freq = self.freq.max(1.0).log2();
+        self.log_freq  = log_freq     + (self.log_freq - log_freq)        * smooth;
+        self.gain_smooth = self.db_gain + (self.gain_smooth - self.db_gain) * smooth;
+        self.q_smooth  = self.q       + (self.q_smooth - self.q)          * smooth;
+        let freq = self.log_freq.exp2();
+        let q = self.q_smooth.max(0.01);
+
+        // the peaking and shelf biquads apply twice their gain in decibels
+        let n = self.active_sections;
+        let y = match self.active_type {
+            BandType::Peaking => {
+                let f = &mut self.peaking;
+                f.cutoff = freq;
+                f.q = q;
+                f.db_gain = 0.5 * self.gain_smooth;
+                f.step(input)
+            },
+            BandType::LowShelf => {
+                let f = &mut self.low_shelf;
+                f.cutoff = freq;
+                f.q = q;
+                f.db_gain = 0.5 * self.gain_smooth;
+                f.step(input)
+            },
+            BandType::HighShelf => {
+                let f = &mut self.high_shelf;
+                f.cutoff = freq;
+                f.q = q;
+                f.db_gain = 0.5 * self.gain_smooth;
+                f.step(input)
+            },
+            BandType::LowPass => {
+                self.low_pass.iter_mut().take(n).enumerate().fold(input, |x, (i, f)| {
+                    f.cutoff = freq;
+                    f.q = Self::section_q(q, i, n);
+                    f.step(x)
+                })
+            },
+            BandType::HighPass => {
+                self.high_pass.iter_mut().take(n).enumerate().fold(input, |x, (i, f)| {
+                    f.cutoff = freq;
+                    f.q = Self::section_q(q, i, n);
+                    f.step(x)
+                })
+            },
+        };
+        input + self.mix * (y - input)
+    }
+
+    // complex frequency response of the band with its target parameters
+    fn response(&self, freq: f64, sr: f64) -> (f64, f64) {
+        if !self.enabled { return (1.0, 0.0); }
+        let omega = f_to_omega(freq, sr);
+        let q = self.q.max(0.01);
+        match self.band_type {
+            // the peaking and shelf biquads apply twice their gain in decibels
+            BandType::Peaking => {
+                let mut f = BiquadPeaking::new();
+                f.set_sr(sr);
+                f.cutoff = self.freq;
+                f.q = q;
+                f.db_gain = 0.5 * self.db_gain;
+                let (a, b) = f.coeffs();
+                biquad_response(a, b, omega)
+            },
+            BandType::LowShelf => {
+                let mut f = BiquadLowShelf::new();
+                f.set_sr(sr);
+                f.cutoff = self.freq;
+                f.q = q;
+                f.db_gain = 0.5 * self.db_gain;
+                let (a, b) = f.coeffs();
+                biquad_response(a, b, omega)
+            },
+            BandType::HighShelf => {
+                let mut f = BiquadHighShelf::new();
+                f.set_sr(sr);
+                f.cutoff = self.freq;
+                f.q = q;
+                f.db_gain = 0.5 * self.db_gain;
+                let (a, b) = f.coeffs();
+                biquad_response(a, b, omega)
+            },
+            BandType::LowPass => {
+                let n = self.sections();
+                (0..n).fold((1.0, 0.0), |h, i| {
+                    let mut f = BiquadLowPass::new();
+                    f.set_sr(sr);
+                    f.cutoff = self.freq;
+                    f.q = Self::section_q(q, i, n);
+                    let (a, b) = f.coeffs();
+                    c_mul(h, biquad_response(a, b, omega))
+                })
+            },
+            BandType::HighPass => {
+                let n = self.sections();
+                (0..n).fold((1.0, 0.0), |h, i| {
+                    let mut f = BiquadHighPass::new();
+                    f.set_sr(sr);
+                    f.cutoff = self.freq;
+                    f.q = Self::section_q(q, i, n);
+                    let (a, b) = f.coeffs();
+                    c_mul(h, biquad_response(a, b, omega))
+                })
+            },
+        }
+    }
+}
+
+// evaluates the transfer function of a biquad at the normalized frequency omega,
+// as a complex number
+fn biquad_response(a: [f64; 3], b: [f64; 3], omega: f64) -> (f64, f64) {
+    // z^-1 and z^-2 on the unit circle
+    let z1 = (omega.cos(), -omega.sin());
+    let z2 = c_mul(z1, z1);
+    let num = (b[0] + b[1] * z1.0 + b[2] * z2.0, b[1] * z1.1 + b[2] * z2.1);
+    let den = (a[0] + a[1] * z1.0 + a[2] * z2.0, a[1] * z1.1 + a[2] * z2.1);
+
+    // complex division
+    let den_sq = den.0 * den.0 + den.1 * den.1;
+    let num_den = c_mul(num, (den.0, -den.1));
+    (num_den.0 / den_sq, num_den.1 / den_sq)
+}
+
+
+/// Parametric EQ with a configurable number of bands, processed in series.
+///
+/// # Examples
+/// ```
+/// use dsp_lab::effects::eq::{ParametricEq, BandType};
+/// use dsp_lab::traits::Process;
+/// let mut eq = ParametricEq::new(2);
+/// if let Some(band) = eq.band(0) {
+///     band.band_type = BandType::LowShelf;
+///     band.freq = 200.0;
+///     band.db_gain = 6.0;
+/// }
+/// assert!((eq.response_db(20.0) - 6.0).abs() < 0.1);
+/// assert!(eq.response_db(10000.0).abs() < 0.1);
+/// ```
+pub struct ParametricEq {
+    bands: Vec<EqBand>,
+    sr: f64,
+    smooth: f64,
+}
+
+impl ParametricEq {
+    /// Creates an EQ with `num_bands` flat peaking bands, spread logarithmically
+    /// between 100Hz and 10kHz.
+    pub fn new(num_bands: usize) -> Self {
+        let mut ret = Self {
+            bands: Vec::new(),
+            sr: 44100.0,
+            smooth: 0.0,
+        };
+        for i in 0..num_bands {
+            let x = if num_bands > 1 { i as f64 / (num_bands - 1) as f64 } else { 0.5 };
+            let freq = 100.0 * 100.0_f64.powf(x);
+            ret.bands.push(EqBand::new(freq, ret.sr));
+        }
+        ret.set_sr(44100.0);
+        ret
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        self.sr = sr;
+        self.smooth = (-1.0 / (SMOOTHING_MS * 0.001 * sr)).exp();
+        for band in self.bands.iter_mut() {
+            band.set_sr(sr);
+        }
+    }
+
+    /// Number of bands in the EQ.
+    pub fn num_bands(&self) -> usize { self.bands.len() }
+
+    /// Adds a new flat peaking band at 1kHz.
+    /// # Returns
+    /// - index of the new band
+    pub fn add_band(&mut self) -> usize {
+        self.bands.push(EqBand::new(1000.0, self.sr));
+        self.bands.len() - 1
+    }
+
+    /// Removes a band. This is not smoothed, to remove a band without clicks,
+    /// disable it first and wait for it to fade out.
+    /// # Returns
+    /// - boolean representing wether the band existed in the first place.
+    /// # Side-effects
+    /// The vector of bands is shifted, thus all indexes greater than the one
+    /// removed are shifted with it.
+    pub fn remove_band(&mut self, index: usize) -> bool {
+        if index < self.bands.len() {
+            self.bands.remove(index);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Gives access to the parameters of a band, or `None` if it doesn't exist.
+    pub fn band(&mut self, index: usize) -> Option<&mut EqBand> {
+        self.bands.get_mut(index)
+    }
+
+    /// Combined magnitude response of all bands at `freq` hertz, in decibels.
+    /// Uses the target parameters, so it doesn't wait for the smoothing.
+    pub fn response_db(&self, freq: f64) -> f64 {
+        let h = self.bands.iter()
+            .fold((1.0, 0.0), |h, band| c_mul(h, band.response(freq, self.sr)));
+        10.0 * (h.0 * h.0 + h.1 * h.1).log10()
+    }
+}
+
+impl Process<f64> for ParametricEq {
+    fn step(&mut self, input: f64) -> f64 {
+        let smooth = self.smooth;
+        let sr = self.sr;
+        self.bands.iter_mut().fold(input, |x, band| band.step(x, smooth, sr))
+    }
+}
@@ -0,0 +1,144 @@
+//! Formant filter, imposes the resonances of a human voice singing a vowel on
+//! any input.
+//!
+//! Five parallel band-pass resonators are tuned to the formants of a vowel, taken
+//! from tables for different voice types. The vowel is a continuous parameter,
+//! so the filter can morph smoothly from one vowel to the next. Works best on
+//! harmonically rich inputs, like saw waves or pulse trains.
+
+mod tables;
+
+use crate::traits::Process;
+use crate::core::lin_filter::BiquadBandPass;
+use crate::utils::conversion::db_to_gain;
+use crate::effects::formant::tables::{VowelTable, SOPRANO, ALTO, COUNTERTENOR,
+    TENOR, BASS};
+
+// time constant of parameter smoothing, in milliseconds
+const SMOOTHING_MS: f64 = 20.0;
+
+/// Used to select the voice type of the formant tables.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Voice {
+    Soprano,
+    Alto,
+    Countertenor,
+    Tenor,
+    Bass,
+}
+
+/// Vowels available in the formant tables, in the order in which the `vowel`
+/// parameter of `FormantFilter` morphs through them.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Vowel {
+    A,
+    E,
+    I,
+    O,
+    U,
+}
+
+/// Vowel filter, made of five parallel band-pass resonators.
+///
+/// - `voice`: voice type, selects the formant table
+/// - `vowel`: continuous vowel position, 0 is "a", 1 is "e", 2 is "i", 3 is "o"
+///   and 4 is "u", values in between morph between neighbouring vowels
+///
+/// Changes to both parameters are smoothed, formant frequencies are morphed
+/// in the log domain and formant gains in dB, so that the morph sounds even.
+///
+/// # Examples
+/// ```
+/// use dsp_lab::effects::formant::{FormantFilter, Voice, Vowel};
+/// use dsp_lab::traits::Process;
+/// let mut filter = FormantFilter::new();
+/// filter.voice = Voice::Bass;
+/// filter.set_vowel(Vowel::O);
+/// filter.vowel += 0.5;    // halfway between "o" and "u"
+/// let y = filter.step(1.0);
+/// ```
+pub struct FormantFilter {
+    resonators: [BiquadBandPass; 5],
+    // smoothed formants as (log2 frequency, gain in dB, bandwidth in Hz)
+    formants: [(f64, f64, f64); 5],
+    smooth: f64,
+
+    pub voice: Voice,
+    pub vowel: f64,
+}
+
+impl FormantFilter {
+    /// Creates a soprano formant filter on the vowel "a".
+    pub fn new() -> Self {
+        let mut ret = Self {
+            resonators: [BiquadBandPass::new(), BiquadBandPass::new(),
+                BiquadBandPass::new(), BiquadBandPass::new(), BiquadBandPass::new()],
+            formants: [(0.0, 0.0, 0.0); 5],
+            smooth: 0.0,
+
+            voice: Voice::Soprano,
+            vowel: 0.0,
+        };
+        ret.set_sr(44100.0);
+        ret.formants = ret.target();
+        ret
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        for res in self.resonators.iter_mut() { res.set_sr(sr); }
+        self.smooth = (-1.0 / (SMOOTHING_MS * 0.001 * sr)).exp();
+    }
+
+    /// Moves the vowel parameter exactly onto one of the vowels.
+    pub fn set_vowel(&mut self, vowel: Vowel) {
+        self.vowel = vowel as usize as f64;
+    }
+
+    // interpolates the formant table at the current parameters
+    fn target(&self) -> [(f64, f64, f64); 5] {
+        let table: &VowelTable = match self.voice {
+            Voice::Soprano      => &SOPRANO,
+            Voice::Alto         => &ALTO,
+            Voice::Countertenor => &COUNTERTENOR,
+            Voice::Tenor        => &TENOR,
+            Voice::Bass         => &BASS,
+        };
+        let pos = self.vowel.clamp(0.0, 4.0);
+        let i = (pos.floor() as usize).min(3);
+        let x = pos - i as f64;
+
+        let mut ret = [(0.0, 0.0, 0.0); 5];
+        for (k, f) in ret.iter_mut().enumerate() {
+            let (f_a, g_a, bw_a) = table[i][k];
+            let (f_b, g_b, bw_b) = table[i + 1][k];
+            *f = (
+                f_a.log2() * (1.0 - x) + f_b.log2() * x,
+                g_a * (1.0 - x) + g_b * x,
+                bw_a * (1.0 - x) + bw_b * x,
+            );
+        }
+        ret
+    }
+}
+
+impl Process<f64> for FormantFilter {
+    fn step(&mut self, input: f64) -> f64 {
+        let target = self.target();
+        let s = self.smooth;
+        let mut accum = 0.0;
+        for ((res, f), t) in self.resonators.iter_mut()
+            .zip(self.formants.iter_mut())
+            .zip(target.iter())
+        {
+            f.0 = t.0 + (f.0 - t.0) * s;
+            f.1 = t.1 + (f.1 - t.1) * s;
+            f.2 = t.2 + (f.2 - t.2) * s;
+
+            let freq = f.0.exp2();
+            res.cutoff = freq;
+            res.q = freq / f.2;
+            accum += res.step(input) * db_to_gain(f.1);
+        }
+        accum
+    }
+}
@@ -0,0 +1,45 @@
+// Formant tables, after the vowel formant table in the Csound manual appendix.
+// Each voice has the vowels a, e, i, o, u in this order, and each vowel has 5
+// formants as (frequency in Hz, gain in dB, bandwidth in Hz).
+
+pub type VowelTable = [[(f64, f64, f64); 5]; 5];
+
+pub const SOPRANO: VowelTable = [
+    [(800.0,   0.0,  80.0), (1150.0,  -6.0,  90.0), (2900.0, -32.0, 120.0), (3900.0, -20.0, 130.0), (4950.0, -50.0, 140.0)],
+    [(350.0,   0.0,  60.0), (2000.0, -20.0, 100.0), (2800.0, -15.0, 120.0), (3600.0, -40.0, 150.0), (4950.0, -56.0, 200.0)],
+    [(270.0,   0.0,  60.0), (2140.0, -12.0,  90.0), (2950.0, -26.0, 100.0), (3900.0, -26.0, 120.0), (4950.0, -44.0, 120.0)],
+    [(450.0,   0.0,  70.0), ( 800.0, -11.0,  80.0), (2830.0, -22.0, 100.0), (3800.0, -22.0, 130.0), (4950.0, -50.0, 135.0)],
+    [(325.0,   0.0,  50.0), ( 700.0, -16.0,  60.0), (2700.0, -35.0, 170.0), (3800.0, -40.0, 180.0), (4950.0, -60.0, 200.0)],
+];
+
+pub const ALTO: VowelTable = [
+    [(800.0,   0.0,  80.0), (1150.0,  -4.0,  90.0), (2800.0, -20.0, 120.0), (3500.0, -36.0, 130.0), (4950.0, -60.0, 140.0)],
+    [(400.0,   0.0,  60.0), (1600.0, -24.0,  80.0), (2700.0, -30.0, 120.0), (3300.0, -35.0, 150.0), (4950.0, -60.0, 200.0)],
+    [(350.0,   0.0,  50.0), (1700.0, -20.0, 100.0), (2700.0, -30.0, 120.0), (3700.0, -36.0, 150.0), (4950.0, -60.0, 200.0)],
+    [(450.0,   0.0,  70.0), ( 800.0,  -9.0,  80.0), (2830.0, -16.0, 100.0), (3500.0, -28.0, 130.0), (4950.0, -55.0, 135.0)],
+    [(325.0,   0.0,  50.0), ( 700.0, -12.0,  60.0), (2530.0, -30.0, 170.0), (3500.0, -40.0, 180.0), (4950.0, -64.0, 200.0)],
+];
+
+pub const COUNTERTENOR: VowelTable = [
+    [(660.0,   0.0,  80.0), (1120.0,  -6.0,  90.0), (2750.0, -23.0, 120.0), (3000.0, -24.0, 130.0), (3350.0, -38.0, 140.0)],
+    [(440.0,   0.0,  70.0), (1800.0, -14.0,  80.0), (2700.0, -18.0, 100.0), (3000.0, -20.0, 120.0), (3300.0, -20.0, 120.0)],
+    [(270.0,   0.0,  40.0), (1850.0, -24.0,  90.0), (2900.0, -24.0, 100.0), (3350.0, -36.0, 120.0), (3590.0, -36.0, 120.0)],
+    [(430.0,   0.0,  40.0), ( 820.0, -10.0,  80.0), (2700.0, -26.0, 100.0), (3000.0, -22.0, 120.0), (3300.0, -34.0, 120.0)],
+    [(370.0,   0.0,  40.0), ( 630.0, -20.0,  60.0), (2750.0, -23.0, 100.0), (3000.0, -30.0, 120.0), (3400.0, -34.0, 120.0)],
+];
+
+pub const TENOR: VowelTable = [
+    [(650.0,   0.0,  80.0), (1080.0,  -6.0,  90.0), (2650.0,  -7.0, 120.0), (2900.0,  -8.0, 130.0), (3250.0, -22.0, 140.0)],
+    [(400.0,   0.0,  70.0), (1700.0, -14.0,  80.0), (2600.0, -12.0, 100.0), (3200.0, -14.0, 120.0), (3580.0, -20.0, 120.0)],
+    [(290.0,   0.0,  40.0), (1870.0, -15.0,  90.0), (2800.0, -18.0, 100.0), (3250.0, -20.0, 120.0), (3540.0, -30.0, 120.0)],
+    [(400.0,   0.0,  40.0), ( 800.0, -10.0,  80.0), (2600.0, -12.0, 100.0), (2800.0, -12.0, 120.0), (3000.0, -26.0, 120.0)],
+    [(350.0,   0.0,  40.0), ( 600.0, -20.0,  60.0), (2700.0, -17.0, 100.0), (2900.0, -14.0, 120.0), (3300.0, -26.0, 120.0)],
+];
+
+pub const BASS: VowelTable = [
+    [(600.0,   0.0,  60.0), (1040.0,  -7.0,  70.0), (2250.0,  -9.0, 110.0), (2450.0,  -9.0, 120.0), (2750.0, -20.0, 130.0)],
+    [(400.0,   0.0,  40.0), (1620.0, -12.0,  80.0), (2400.0,  -9.0, 100.0), (2800.0, -12.0, 120.0), (3100.0, -18.0, 120.0)],
+    [(250.0,   0.0,  60.0), (1750.0, -30.0,  90.0), (2600.0, -16.0, 100.0), (3050.0, -22.0, 120.0), (3340.0, -28.0, 120.0)],
+    [(400.0,   0.0,  40.0), ( 750.0, -11.0,  80.0), (2400.0, -21.0, 100.0), (2600.0, -20.0, 120.0), (2900.0, -40.0, 120.0)],
+    [(350.0,   0.0,  40.0), ( 600.0, -20.0,  80.0), (2400.0, -32.0, 100.0), (2675.0, -28.0, 120.0), (2950.0, -36.0, 120.0)],
+];
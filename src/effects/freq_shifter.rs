@@ -0,0 +1,76 @@
+//! Bode-style frequency shifter.
+//! 
+//! Unlike pitch shifting, frequency shifting moves all partials by the same
+//! amount in hertz, which breaks harmonic relationships, giving metallic and
+//! inharmonic sounds. Small shifts of a few hertz give a phaser-like sweeping
+//! effect, especially with feedback.
+//! 
+//! It is implemented as single-sideband modulation: the input is turned into
+//! an analytic signal with a Hilbert transformer, and multiplied by a complex
+//! carrier. The sum and difference of the quadrature products give the upper
+//! and lower sidebands.
+
+use crate::traits::{Process, Source};
+use crate::core::lin_filter::HilbertAp;
+use crate::core::osc::RampCore;
+
+/// Bode-style frequency shifter, with upwards and downwards shifted outputs,
+/// and feedback.
+/// 
+/// The `step()` output is shifted up by the shift frequency, the output shifted
+/// down by the same amount is available in `down_aux`. Negative shifts are
+/// allowed, and swap the two outputs.
+/// 
+/// `feedback` feeds the upwards shifted output back into the input, so each
+/// pass through the loop is shifted further, creating barber-pole like spirals.
+/// It is clamped to (-1, 1).
+pub struct FreqShifter {
+    hilbert: HilbertAp,
+    carrier: RampCore,
+    up_z1: f64,
+    pub feedback: f64,
+
+    // auxiliary output
+    pub down_aux: f64,
+}
+
+impl FreqShifter {
+    pub fn new() -> Self {
+        let mut ret = Self {
+            hilbert: HilbertAp::new(),
+            carrier: RampCore::new(),
+            up_z1: 0.0,
+            feedback: 0.0,
+            down_aux: 0.0,
+        };
+        ret.carrier.set_freq(0.0);
+        ret
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        self.carrier.sr = sr;
+    }
+
+    /// Change the amount of frequency shift, in hertz. This is a method and
+    /// not a field, because the frequency is stored internally by the carrier.
+    pub fn set_shift(&mut self, shift: f64) {
+        self.carrier.set_freq(shift);
+    }
+}
+
+impl Process<f64> for FreqShifter {
+    fn step(&mut self, input: f64) -> f64 {
+        let fb = self.feedback.clamp(-0.999, 0.999);
+        let (re, im) = self.hilbert.step(input + fb * self.up_z1);
+
+        // multiply analytic signal by the complex carrier
+        let phi = self.carrier.step();
+        let (sin, cos) = phi.sin_cos();
+        let up   = re * cos - im * sin;
+        let down = re * cos + im * sin;
+
+        self.up_z1 = up;
+        self.down_aux = down;
+        up
+    }
+}
@@ -1 +1,9 @@
-//! This module contains pre-made effects.
\ No newline at end of file
+//! Complete effects, built by combining the primitives in the `core` module.
+
+pub mod freq_shifter;           // Bode-style frequency shifter, single-sideband modulation
+pub mod eq;                     // multi-band parametric EQ
+pub mod dynamic_eq;             // dynamic EQ, de-essing and resonance taming
+pub mod phaser;                 // phaser, modulated all-pass chain
+pub mod formant;                // formant filter, vowel morphing
+pub mod wavefolder;             // West Coast wavefolder
+pub mod bitcrusher;             // bit depth and sample rate reduction
@@ -0,0 +1,254 @@
+//! Phaser, built from a chain of modulated all-pass stages.
+//!
+//! The all-pass chain shifts the phase of the signal while leaving the magnitude
+//! untouched, mixing it back with the dry signal creates notches where the two
+//! are out of phase. An LFO sweeps the all-pass cutoff, moving the notches up
+//! and down the spectrum, and feedback around the chain turns the notches into
+//! resonant peaks.
+
+use crate::traits::{Process, Source};
+use crate::core::lin_filter::{AllPass1P, BiquadAllPass, LowPass1P};
+use crate::core::osc::{ParOsc, AsymTriOsc};
+use crate::core::chaos::SnhRandom;
+use crate::utils::math::x_fade;
+
+/// Maximum number of all-pass stages in a phaser.
+pub const MAX_STAGES: usize = 24;
+
+/// Used to select the all-pass stages of a phaser.
+///
+/// - First: first order stages, each pair of stages makes one notch, classic
+///   phaser sound
+/// - Second: second order stages, each stage makes one notch, with the width
+///   set by `q`
+#[derive(Clone, Copy, PartialEq)]
+pub enum PhaserOrder {
+    First,
+    Second,
+}
+
+/// Used to select the LFO that sweeps a phaser.
+///
+/// - Sine: parabolic sine from `core::osc::ParOsc`
+/// - Triangle: triangle from `core::osc::AsymTriOsc`
+/// - SampleAndHold: stepped random from `core::chaos::SnhRandom`
+/// - SmoothRandom: sample and hold random, smoothed by a low-pass
+#[derive(Clone, Copy, PartialEq)]
+pub enum LfoSource {
+    Sine,
+    Triangle,
+    SampleAndHold,
+    SmoothRandom,
+}
+
+/// Mono phaser.
+///
+/// - `freq`: center frequency of the sweep in hertz
+/// - `depth`: width of the sweep in octaves, around the center frequency
+/// - `feedback`: feedback around the all-pass chain, clamped between -0.95 and
+///   0.95, negative values move the resonances between the notches
+/// - `q`: Q-factor of second order stages, ignored by first order stages
+/// - `mix`: dry/wet mix, 0.5 gives the deepest notches
+/// - `order`: order of the all-pass stages
+/// - `lfo_source`: which LFO sweeps the stages
+///
+/// # Examples
+/// ```
+/// use dsp_lab::effects::phaser::{Phaser, LfoSource};
+/// use dsp_lab::traits::Process;
+/// let mut phaser = Phaser::new(0);
+/// phaser.set_stages(8);
+/// phaser.set_rate(0.3);
+/// phaser.lfo_source = LfoSource::Triangle;
+/// let y = phaser.step(1.0);
+/// ```
+pub struct Phaser {
+    ap_1p: Vec<AllPass1P>,
+    ap_2p: Vec<BiquadAllPass>,
+    stages: usize,
+    fb_z1: f64,
+
+    sine: ParOsc,
+    tri: AsymTriOsc,
+    snh: SnhRandom,
+    snh_smooth: LowPass1P,
+    rate: f64,
+
+    pub freq: f64,
+    pub depth: f64,
+    pub feedback: f64,
+    pub q: f64,
+    pub mix: f64,
+    pub order: PhaserOrder,
+    pub lfo_source: LfoSource,
+}
+
+impl Phaser {
+    /// Creates a 4-stage first order phaser, `seed` seeds the random LFOs.
+    pub fn new(seed: u8) -> Self {
+        let mut ret = Self {
+            ap_1p: (0..MAX_STAGES).map(|_| AllPass1P::new()).collect(),
+            ap_2p: (0..MAX_STAGES).map(|_| BiquadAllPass::new()).collect(),
+            stages: 4,
+            fb_z1: 0.0,
+
+            sine: ParOsc::new(),
+            tri: AsymTriOsc::new(),
+            snh: SnhRandom::new(44100.0, seed),
+            snh_smooth: LowPass1P::new(),
+            rate: 0.5,
+
+            freq: 800.0,
+            depth: 3.0,
+            feedback: 0.0,
+            q: 0.5,
+            mix: 0.5,
+            order: PhaserOrder::First,
+            lfo_source: LfoSource::Sine,
+        };
+        ret.set_sr(44100.0);
+        ret
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        for ap in self.ap_1p.iter_mut() { ap.set_sr(sr); }
+        for ap in self.ap_2p.iter_mut() { ap.set_sr(sr); }
+        self.sine.set_sr(sr);
+        self.tri.set_sr(sr);
+        self.snh.set_sr(sr);
+        self.snh_smooth.set_sr(sr);
+        self.set_rate(self.rate);
+    }
+
+    /// Sets the number of all-pass stages, clamped between 2 and 24. This is a
+    /// method and not a field, because of the clamping.
+    pub fn set_stages(&mut self, stages: usize) {
+        self.stages = stages.clamp(2, MAX_STAGES);
+    }
+
+    /// Number of active all-pass stages.
+    pub fn stages(&self) -> usize { self.stages }
+
+    /// Sets the LFO rate in hertz, for all LFO sources. This is a method and not
+    /// a field, because it is forwarded to the LFOs.
+    pub fn set_rate(&mut self, rate: f64) {
+        self.rate = rate;
+        self.sine.set_freq(rate);
+        self.tri.set_freq(rate);
+        self.snh.set_freq(rate);
+        // smooth the random steps over roughly one period
+        self.snh_smooth.set_cutoff(rate);
+    }
+
+    /// Sets the phase of the periodic LFOs, in radians. Has no effect on the
+    /// random LFOs.
+    pub fn set_lfo_phase(&mut self, phase: f64) {
+        self.sine.set_phase(phase);
+        self.tri.set_phase(phase);
+    }
+
+    // steps the selected LFO, between -1 and 1
+    fn step_lfo(&mut self) -> f64 {
+        match self.lfo_source {
+            LfoSource::Sine => self.sine.step(),
+            LfoSource::Triangle => self.tri.step(),
+            // the random source is between 0 and 1
+            LfoSource::SampleAndHold => 2.0 * self.snh.step() - 1.0,
+            LfoSource::SmoothRandom => {
+                let x = 2.0 * self.snh.step() - 1.0;
+                self.snh_smooth.step(x)
+            },
+        }
+    }
+}
+
+impl Process<f64> for Phaser {
+    fn step(&mut self, input: f64) -> f64 {
+        // sweep exponentially, so that it sounds even across the spectrum
+        let lfo = self.step_lfo().clamp(-1.0, 1.0);
+        let cutoff = self.freq * (0.5 * self.depth * lfo).exp2();
+
+        let mut wet = input + self.feedback.clamp(-0.95, 0.95) * self.fb_z1;
+        match self.order {
+            PhaserOrder::First => {
+                for ap in self.ap_1p.iter_mut().take(self.stages) {
+                    ap.cutoff = cutoff;
+                    wet = ap.step(wet);
+                }
+            },
+            PhaserOrder::Second => {
+                for ap in self.ap_2p.iter_mut().take(self.stages) {
+                    ap.cutoff = cutoff;
+                    ap.q = self.q;
+                    wet = ap.step(wet);
+                }
+            },
+        }
+        self.fb_z1 = wet;
+
+        x_fade(input, self.mix, wet)
+    }
+}
+
+
+/// Stereo phaser, made of two `Phaser` sharing their parameters, with the LFO
+/// of the right channel offset from the left one.
+///
+/// Parameters are set on the left phaser through `params()` and copied to the
+/// right phaser on every step.
+///
+/// # Examples
+/// ```
+/// use dsp_lab::effects::phaser::StereoPhaser;
+/// let mut phaser = StereoPhaser::new(0);
+/// phaser.params().set_rate(0.2);
+/// phaser.params().feedback = 0.7;
+/// let (l, r) = phaser.step((1.0, 1.0));
+/// ```
+pub struct StereoPhaser {
+    left: Phaser,
+    right: Phaser,
+}
+
+impl StereoPhaser {
+    /// Creates a stereo phaser with a 90 degree LFO offset, `seed` seeds the
+    /// random LFOs, which are different for each channel.
+    pub fn new(seed: u8) -> Self {
+        let mut ret = Self {
+            left: Phaser::new(seed),
+            right: Phaser::new(seed.wrapping_add(1)),
+        };
+        ret.set_stereo_phase(std::f64::consts::FRAC_PI_2);
+        ret
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        self.left.set_sr(sr);
+        self.right.set_sr(sr);
+    }
+
+    /// Gives access to the parameters shared by both channels.
+    pub fn params(&mut self) -> &mut Phaser { &mut self.left }
+
+    /// Sets the LFO phase offset of the right channel in radians, and restarts
+    /// both LFOs so that the offset is exact.
+    pub fn set_stereo_phase(&mut self, phase: f64) {
+        self.left.set_lfo_phase(0.0);
+        self.right.set_lfo_phase(phase);
+    }
+
+    pub fn step(&mut self, input: (f64, f64)) -> (f64, f64) {
+        let (l, r) = (&self.left, &mut self.right);
+        r.freq = l.freq;
+        r.depth = l.depth;
+        r.feedback = l.feedback;
+        r.q = l.q;
+        r.mix = l.mix;
+        r.order = l.order;
+        r.lfo_source = l.lfo_source;
+        r.stages = l.stages;
+        if r.rate != l.rate { r.set_rate(l.rate); }
+
+        (self.left.step(input.0), self.right.step(input.1))
+    }
+}
@@ -0,0 +1,184 @@
+//! Wavefolder, the West Coast answer to distortion.
+//!
+//! Instead of clipping, the signal is reflected back each time it crosses a
+//! threshold, so that driving it harder adds more and more folds, sweeping
+//! through a bright spectrum that moves in a very characteristic way. Modelled
+//! on the Buchla 259, where folding cells with increasing thresholds are summed
+//! in parallel with alternating polarity, each cell adding one fold. The number
+//! of cells limits the number of folds, past the last cell the output stays flat
+//! like in the Serge wavefolder.
+//!
+//! Folding generates a lot of high harmonics, so the folder is anti-aliased
+//! twice: with first order ADAA, and by running at an oversampled rate.
+
+use crate::traits::Process;
+use crate::core::lin_filter::DcBlock;
+use crate::core::oversampling::{Oversampler, OversamplingFactor, OversamplingPhase};
+use crate::core::waveshapers::{AdaaCurve, Adaa1};
+
+/// Maximum number of folding stages.
+pub const MAX_FOLD_STAGES: usize = 8;
+
+// width of the rounded knee of each folding cell
+const KNEE: f64 = 0.1;
+
+// smooth ramp, approximates max(u, 0) with a rounded knee, and its first two
+// antiderivatives
+fn ramp(u: f64) -> f64 {
+    0.5 * (u + (u * u + KNEE * KNEE).sqrt())
+}
+
+fn ramp_1(u: f64) -> f64 {
+    let s = (u * u + KNEE * KNEE).sqrt();
+    0.25 * (u * u + u * s + KNEE * KNEE * (u / KNEE).asinh())
+}
+
+fn ramp_2(u: f64) -> f64 {
+    let s = (u * u + KNEE * KNEE).sqrt();
+    0.25 * ((u * u * u + s * s * s) / 3.0 + KNEE * KNEE * (u * (u / KNEE).asinh() - s))
+}
+
+/// Folding curve of `Wavefolder`, as an ADAA curve, so that it can also be used
+/// with `core::waveshapers::Adaa2`.
+///
+/// The curve rises with unit slope up to 1, then each stage reverses the slope
+/// at the next odd threshold (1, 3, 5, ...), so the output folds between -1 and
+/// 1. The last stage flattens the curve instead, so it stays within -1 and 1.
+///
+/// - `stages`: number of folding stages, clamped between 1 and `MAX_FOLD_STAGES`
+/// - `bias`: offset of the input, makes the folds asymmetric
+pub struct FoldCurve {
+    pub stages: usize,
+    pub bias: f64,
+}
+
+impl FoldCurve {
+    pub fn new() -> Self {
+        Self {
+            stages: 4,
+            bias: 0.0,
+        }
+    }
+
+    // weight of the folding cell `k`, the cells alternate polarity and the last
+    // one only has half weight, so that it flattens the curve instead of folding
+    fn weight(&self, k: usize) -> f64 {
+        let stages = self.stages.clamp(1, MAX_FOLD_STAGES);
+        let w = if k + 1 == stages { 1.0 } else { 2.0 };
+        if k % 2 == 1 { w } else { -w }
+    }
+
+    fn threshold(k: usize) -> f64 { (2 * k + 1) as f64 }
+}
+
+impl AdaaCurve for FoldCurve {
+    fn f(&self, x: f64) -> f64 {
+        let x = x + self.bias;
+        (0..self.stages.clamp(1, MAX_FOLD_STAGES)).fold(x, |acc, k| {
+            let t = Self::threshold(k);
+            acc + self.weight(k) * (ramp(x - t) - ramp(-x - t))
+        })
+    }
+
+    fn f1(&self, x: f64) -> f64 {
+        let x = x + self.bias;
+        (0..self.stages.clamp(1, MAX_FOLD_STAGES)).fold(0.5 * x * x, |acc, k| {
+            let t = Self::threshold(k);
+            acc + self.weight(k) * (ramp_1(x - t) + ramp_1(-x - t))
+        })
+    }
+
+    fn f2(&self, x: f64) -> f64 {
+        let x = x + self.bias;
+        (0..self.stages.clamp(1, MAX_FOLD_STAGES)).fold(x * x * x / 6.0, |acc, k| {
+            let t = Self::threshold(k);
+            acc + self.weight(k) * (ramp_2(x - t) - ramp_2(-x - t))
+        })
+    }
+}
+
+
+/// Wavefolder, with ADAA and oversampling.
+///
+/// - `fold`: gain before folding, at 1 a full scale signal just reaches the
+///   first fold, each increase by 2 adds a fold
+/// - `symmetry`: offset before folding, between -1 and 1, moves the folds off
+///   center, adding even harmonics. The resulting DC offset is removed.
+///
+/// # Caveats
+/// Defaults to 2x oversampling with minimum phase filters, which together with
+/// ADAA is enough for most material. Use `set_oversampling()` for cleaner
+/// results with very high fold amounts, or for lower latency.
+///
+/// # Examples
+/// Folding a sine oscillator:
+/// ```
+/// use dsp_lab::effects::wavefolder::Wavefolder;
+/// use dsp_lab::core::osc::ParOsc;
+/// use dsp_lab::traits::{Process, Source};
+/// let mut osc = ParOsc::new();
+/// osc.set_freq(110.0);
+/// let mut folder = Wavefolder::new();
+/// folder.set_stages(6);
+/// folder.fold = 5.0;
+/// folder.symmetry = 0.2;
+/// let y = folder.step(osc.step());
+/// ```
+pub struct Wavefolder {
+    folder: Oversampler<Adaa1<FoldCurve>>,
+    dc_block: DcBlock,
+    stages: usize,
+
+    pub fold: f64,
+    pub symmetry: f64,
+}
+
+impl Wavefolder {
+    /// Creates a 4-stage wavefolder, with a fold amount of 1.
+    pub fn new() -> Self {
+        Self {
+            folder: Oversampler::new(Adaa1::new(FoldCurve::new()),
+                OversamplingFactor::X2, OversamplingPhase::Minimum),
+            dc_block: DcBlock::new(),
+            stages: 4,
+
+            fold: 1.0,
+            symmetry: 0.0,
+        }
+    }
+
+    pub fn set_sr(&mut self, sr: f64) {
+        self.dc_block.set_sr(sr);
+    }
+
+    /// Sets the number of folding stages, clamped between 1 and
+    /// `MAX_FOLD_STAGES`. This is a method and not a field, because of the
+    /// clamping.
+    pub fn set_stages(&mut self, stages: usize) {
+        self.stages = stages.clamp(1, MAX_FOLD_STAGES);
+    }
+
+    pub fn stages(&self) -> usize { self.stages }
+
+    /// Changes the oversampling, see `core::oversampling`. Clears the state of
+    /// the oversampling filters.
+    pub fn set_oversampling(&mut self, factor: OversamplingFactor, phase: OversamplingPhase) {
+        self.folder.set_oversampling(factor, phase);
+    }
+
+    /// Latency in samples, from the oversampling filters and ADAA.
+    pub fn latency(&self) -> f64 {
+        self.folder.latency() + 0.5 / self.folder.factor() as usize as f64
+    }
+}
+
+impl Process<f64> for Wavefolder {
+    fn step(&mut self, input: f64) -> f64 {
+        let curve = &mut self.folder.process.curve;
+        curve.stages = self.stages;
+        curve.bias = self.symmetry.clamp(-1.0, 1.0);
+
+        let y = self.folder.step(input * self.fold.max(0.0));
+        self.dc_block.step(y)
+    }
+}
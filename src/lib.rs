@@ -143,6 +143,22 @@ macro_rules! chain_src {
 #[cfg(test)]
 mod tests {
 
+    // fraction of the energy outside the harmonics, the signal has exactly
+    // `cycles` periods
+    fn alias_ratio(x: &[f64], cycles: usize) -> f64 {
+        use num::complex::Complex;
+        use rustfft::FftPlanner;
+        let n = x.len();
+        let mut buf: Vec<Complex<f64>> = x.iter().map(|v| Complex::new(*v, 0.0)).collect();
+        FftPlanner::new().plan_fft_forward(n).process(&mut buf);
+        let (mut alias, mut total) = (0.0, 0.0);
+        for (k, bin) in buf.iter().enumerate().take(n / 2).skip(1) {
+            total += bin.norm_sqr();
+            if k % cycles != 0 { alias += bin.norm_sqr(); }
+        }
+        alias / total
+    }
+
     #[test]
     fn unit_test_process_chain() {
         use crate::traits::{ProcessChain};
@@ -443,5 +459,1298 @@ mod tests {
         }
     }
 
+    #[test]
+    fn unit_test_convolver() {
+        use crate::core::lin_filter::convolution::{Convolver, Partitioning};
+        use crate::core::chaos::NoiseWhite;
+        use crate::traits::{Process, Source};
+        let mut noise = NoiseWhite::new(3);
+        let ir: Vec<f64> = (0..3000).map(|_| noise.step()).collect();
+        let x: Vec<f64> = (0..6000).map(|_| noise.step()).collect();
+
+        for partitioning in [Partitioning::Uniform(128), Partitioning::NonUniform(32)] {
+            let mut conv = Convolver::new();
+            conv.set_partitioning(partitioning);
+            conv.load_ir(&ir);
+            let latency = conv.latency();
+            let y: Vec<f64> = x.iter().map(|s| conv.step(*s)).collect();
+            for n in latency..x.len() {
+                let expected: f64 = ir.iter().enumerate()
+                    .take_while(|(k, _)| *k + latency <= n)
+                    .map(|(k, h)| h * x[n - latency - k])
+                    .sum();
+                assert!((y[n] - expected).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn unit_test_diff_c_diff_hq() {
+        use crate::core::lin_filter::{DiffC, DiffHQ};
+        use crate::traits::Process;
+        use std::f64::consts;
+        let mut diff_c = DiffC::new();
+        let mut diff_hq = DiffHQ::new();
+        let omega = consts::TAU * 1000.0 / 44100.0;
+        for n in 0..1000 {
+            let x = (omega * n as f64).sin();
+            let y_c = diff_c.step(x);
+            let y_hq = diff_hq.step(x);
+            if n > 100 {
+                // centered difference has a response of sin(omega)
+                let d_c = omega.sin() * (omega * (n - 1) as f64).cos();
+                let d_hq = omega * (omega * (n - 16) as f64).cos();
+                assert!((y_c - d_c).abs() < 1e-9);
+                assert!((y_hq - d_hq).abs() < 1e-4 * omega);
+            }
+        }
+    }
+
+    #[test]
+    fn unit_test_integrators() {
+        use crate::core::lin_filter::{IntegTrpz, IntegRK4, IntegLeaky, IntegSafe, OverflowMode};
+        use crate::traits::Process;
+        use std::f64::consts;
+
+        // integral of omega * cos(omega * n) is sin(omega * n)
+        let mut trpz = IntegTrpz::new();
+        let mut rk4 = IntegRK4::new();
+        let omega = consts::TAU * 100.0 / 44100.0;
+        let mut err_trpz: f64 = 0.0;
+        let mut err_rk4: f64 = 0.0;
+        let (mut offs_trpz, mut offs_rk4) = (0.0, 0.0);
+        for n in 0..2000 {
+            let x = omega * (omega * n as f64).cos();
+            let expected = (omega * n as f64).sin();
+            let y_trpz = trpz.step(x) - expected;
+            let y_rk4 = rk4.step(x) - expected;
+
+            // the first few samples integrate the zeroed initial state, so 
+            // measure the error relative to a later sample.
+            if n == 4 {
+                offs_trpz = y_trpz;
+                offs_rk4 = y_rk4;
+            } else if n > 4 {
+                err_trpz = err_trpz.max((y_trpz - offs_trpz).abs());
+                err_rk4 = err_rk4.max((y_rk4 - offs_rk4).abs());
+            }
+        }
+        assert!(err_trpz < 1e-3);
+        assert!(err_rk4 < err_trpz);
+
+        // leak time constant is independent of the sample rate
+        for sr in [44100.0, 192000.0] {
+            let mut leaky = IntegLeaky::new();
+            leaky.set_sr(sr);
+            leaky.set_leak(10.0);
+            let y0 = leaky.step(1.0);
+            let mut y = y0;
+            for _ in 0..(sr * 0.01) as usize { y = leaky.step(0.0); }
+            assert!((y / y0 - (-1.0_f64).exp()).abs() < 1e-3);
+        }
+
+        let mut safe = IntegSafe::new();
+        for _ in 0..10 { assert!(safe.step(0.3) <= 1.0); }
+        safe.overflow_mode = OverflowMode::Wrap;
+        safe.reset();
+        for _ in 0..4 { safe.step(0.3); }
+        assert!((safe.step(0.0) - (-0.8)).abs() < 1e-9);
+        safe.step(f64::NAN);
+        assert!(safe.step(0.0) == 0.0);
+    }
+
+    #[test]
+    fn unit_test_non_lin_integrators() {
+        use crate::core::non_lin_filters::{IntegClip, IntegSat, IntegOTA};
+        use crate::traits::Process;
+        let mut clip = IntegClip::new();
+        let mut sat = IntegSat::new();
+        let mut ota = IntegOTA::new();
+        for _ in 0..100 {
+            assert!(clip.step(0.1) <= 1.0);
+            assert!(sat.step(0.1) <= 1.0);
+        }
+        assert!(clip.step(-0.1) == 0.9);
+        assert!(sat.step(-0.1) < 1.0);
+
+        // small inputs are integrated linearly, large ones are slew limited
+        assert!((ota.step(1e-3) - 1e-3).abs() < 1e-9);
+        assert!(ota.step(100.0) < 1.01);
+    }
+
+    #[test]
+    fn unit_test_va_filters() {
+        use crate::core::non_lin_filters::{LadderLowPass, DiodeLadderLowPass, SallenKeyLowPass};
+        use crate::traits::Process;
+        let mut moog = LadderLowPass::new();
+        let mut diode = DiodeLadderLowPass::new();
+        let mut sk = SallenKeyLowPass::new();
+
+        // unity DC gain with no resonance, small signals are not distorted
+        let (mut y_moog, mut y_diode, mut y_sk) = (0.0, 0.0, 0.0);
+        for _ in 0..10000 {
+            y_moog = moog.step(0.01);
+            y_diode = diode.step(0.01);
+            y_sk = sk.step(0.01);
+        }
+        assert!((y_moog - 0.01).abs() < 1e-5);
+        assert!((y_diode - 0.01).abs() < 1e-5);
+        assert!((y_sk - 0.01).abs() < 1e-5);
+
+        // self-oscillation after an impulse, bounded by the saturation
+        moog.res = 1.2;
+        diode.res = 1.2;
+        sk.res = 1.2;
+        moog.step(1.0);
+        diode.step(1.0);
+        sk.step(1.0);
+        let (mut peak_moog, mut peak_diode, mut peak_sk): (f64, f64, f64) = (0.0, 0.0, 0.0);
+        for i in 0..44100 {
+            let (a, b, c) = (moog.step(0.0), diode.step(0.0), sk.step(0.0));
+            assert!(a.is_finite() && b.is_finite() && c.is_finite());
+            if i > 40000 {
+                peak_moog = peak_moog.max(a.abs());
+                peak_diode = peak_diode.max(b.abs());
+                peak_sk = peak_sk.max(c.abs());
+            }
+        }
+        assert!(peak_moog > 0.01 && peak_moog < 2.0);
+        assert!(peak_diode > 0.01 && peak_diode < 2.0);
+        assert!(peak_sk > 0.01 && peak_sk < 2.0);
+    }
+
+    #[test]
+    fn unit_test_freq_shifter() {
+        use crate::effects::freq_shifter::FreqShifter;
+        use crate::traits::Process;
+        use std::f64::consts;
+        let mut shifter = FreqShifter::new();
+        shifter.set_shift(100.0);
+        let omega = consts::TAU * 1000.0 / 44100.0;
+
+        // count positive zero crossings of both outputs over one second
+        let (mut up_z1, mut down_z1) = (0.0, 0.0);
+        let (mut up_count, mut down_count) = (0, 0);
+        for n in 0..88200 {
+            let up = shifter.step((omega * n as f64).cos());
+            let down = shifter.down_aux;
+            if n >= 44100 {
+                if up_z1 <= 0.0 && up > 0.0 { up_count += 1; }
+                if down_z1 <= 0.0 && down > 0.0 { down_count += 1; }
+            }
+            up_z1 = up;
+            down_z1 = down;
+        }
+        assert!((up_count as i32 - 1100).abs() <= 1);
+        assert!((down_count as i32 - 900).abs() <= 1);
+    }
+
+    #[test]
+    fn unit_test_hilbert() {
+        use crate::core::lin_filter::{HilbertAp, HilbertFir};
+        use std::f64::consts;
+        let mut ap = HilbertAp::new();
+        let mut fir = HilbertFir::new();
+        let omega = consts::TAU * 5000.0 / 44100.0;
+
+        // the analytic signal of a sinusoid has a constant envelope
+        for n in 0..10000 {
+            let x = (omega * n as f64).cos();
+            let (re_ap, im_ap) = ap.step(x);
+            let (re_fir, im_fir) = fir.step(x);
+            if n > 1000 {
+                assert!((re_ap * re_ap + im_ap * im_ap - 1.0).abs() < 0.03);
+                assert!((re_fir * re_fir + im_fir * im_fir - 1.0).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn unit_test_parametric_eq() {
+        use crate::effects::eq::{ParametricEq, BandType};
+        use crate::traits::Process;
+        use std::f64::consts;
+        let mut eq = ParametricEq::new(3);
+        {
+            let band = eq.band(1).unwrap();
+            band.freq = 1000.0;
+            band.db_gain = 6.0;
+            band.q = 2.0;
+        }
+        assert!((eq.response_db(1000.0) - 6.0).abs() < 0.01);
+
+        // the measured gain matches the response, once smoothing settles
+        let omega = consts::TAU * 1000.0 / 44100.0;
+        let mut peak: f64 = 0.0;
+        for n in 0..44100 {
+            let y = eq.step((omega * n as f64).sin());
+            if n > 22050 { peak = peak.max(y.abs()); }
+        }
+        assert!((20.0 * peak.log10() - 6.0).abs() < 0.05);
+
+        // changing the band type doesn't make the output jump
+        {
+            let band = eq.band(1).unwrap();
+            band.band_type = BandType::HighPass;
+            band.slope = 48.0;
+            band.q = 0.707;
+        }
+        let mut y_z1 = eq.step((omega * 44100.0).sin());
+        for n in 44101..88200 {
+            let y = eq.step((omega * n as f64).sin());
+            assert!((y - y_z1).abs() < 0.5);
+            y_z1 = y;
+        }
+        assert!(eq.response_db(100.0) < -60.0);
+        assert!(eq.num_bands() == 3);
+    }
+
+    #[test]
+    fn unit_test_dynamic_eq() {
+        use crate::effects::dynamic_eq::{DynamicEq, DynMode};
+        use crate::traits::Process;
+        use std::f64::consts;
+        let omega = consts::TAU * 6000.0 / 44100.0;
+        let measure = |eq: &mut DynamicEq, amp: f64| {
+            let mut peak: f64 = 0.0;
+            for n in 0..44100 {
+                let y = eq.step(amp * (omega * n as f64).sin());
+                if n > 22050 { peak = peak.max(y.abs()); }
+            }
+            20.0 * (peak / amp).log10()
+        };
+        let setup = |mode: DynMode| {
+            let mut eq = DynamicEq::new(1);
+            let band = eq.band(0).unwrap();
+            band.freq = 6000.0;
+            band.threshold = -20.0;
+            band.ratio = 4.0;
+            band.range = 24.0;
+            band.mode = mode;
+            eq
+        };
+
+        // loud signals in the band are cut, quiet ones are left alone
+        let mut eq = setup(DynMode::Downward);
+        assert!(measure(&mut eq, 1.0) < -10.0);
+        assert!(eq.band(0).unwrap().gain_db() < -10.0);
+        let mut eq = setup(DynMode::Downward);
+        assert!(measure(&mut eq, 0.01).abs() < 0.1);
+
+        // upward mode boosts instead, limited by the range
+        let mut eq = setup(DynMode::Upward);
+        eq.band(0).unwrap().range = 6.0;
+        assert!((measure(&mut eq, 0.5) - 6.0).abs() < 0.1);
+
+        // an external sidechain drives the band instead of the input
+        let mut eq = setup(DynMode::Downward);
+        for n in 0..44100 {
+            let y = eq.step_with_sidechain(0.0, (omega * n as f64).sin());
+            assert!(y == 0.0);
+        }
+        assert!(eq.band(0).unwrap().gain_db() < -10.0);
+
+        // toggling the band fades the gain instead of jumping
+        for enabled in [false, true] {
+            eq.band(0).unwrap().enabled = enabled;
+            let mut last = eq.band(0).unwrap().gain_db();
+            for n in 0..44100 {
+                eq.step_with_sidechain(0.0, (omega * n as f64).sin());
+                let gain = eq.band(0).unwrap().gain_db();
+                assert!((gain - last).abs() < 0.1);
+                last = gain;
+            }
+            assert!(if enabled { last < -10.0 } else { last.abs() < 1e-6 });
+        }
+        assert!(!eq.remove_band(1) && eq.remove_band(0) && eq.num_bands() == 0);
+    }
+
+    #[test]
+    fn unit_test_comb_filters() {
+        use crate::core::reverb::{CombFF, CombFB, CombLBCF};
+        use crate::traits::Process;
+        let impulse = |p: &mut dyn Process<f64>, len: usize| -> Vec<f64> {
+            (0..len).map(|n| p.step(if n == 0 { 1.0 } else { 0.0 })).collect()
+        };
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+
+        // 2ms at 48kHz is 96 samples
+        let mut ff = CombFF::new();
+        ff.set_sr(48000.0);
+        ff.delay = 2.0;
+        let ir = impulse(&mut ff, 300);
+        assert!(close(ir[0], 1.0) && close(ir[96], 0.5) && close(ir[192], 0.0));
+
+        // fractional delays are interpolated
+        ff.delay = 96.5 / 48.0;
+        let ir = impulse(&mut ff, 300);
+        assert!(close(ir[96], 0.25) && close(ir[97], 0.25));
+
+        let mut fb = CombFB::new();
+        fb.set_sr(48000.0);
+        fb.delay = 2.0;
+        let ir = impulse(&mut fb, 300);
+        assert!(close(ir[0], 1.0) && close(ir[96], 0.5) && close(ir[192], 0.25));
+        assert!(close(ir[95], 0.0) && close(ir[97], 0.0));
+
+        // the LBCF output only contains the recirculated signal
+        let mut lbcf = CombLBCF::new();
+        lbcf.set_sr(48000.0);
+        lbcf.delay = 2.0;
+        lbcf.damping = 0.0;
+        lbcf.feedback = 0.5;
+        let ir = impulse(&mut lbcf, 300);
+        assert!(close(ir[0], 0.0) && close(ir[96], 1.0) && close(ir[192], 0.5));
+
+        // the decay time is independent of the delay
+        let mut lbcf = CombLBCF::new();
+        lbcf.set_sr(48000.0);
+        lbcf.delay = 2.0;
+        lbcf.damping = 0.0;
+        lbcf.set_decay(960.0);
+        let ir = impulse(&mut lbcf, 48000);
+        assert!((20.0 * (ir[96 * 481] / ir[96]).log10() + 60.0).abs() < 1e-6);
+
+        // damping makes the echoes duller, but never louder
+        let mut lbcf = CombLBCF::new();
+        let ir = impulse(&mut lbcf, 44100);
+        assert!(ir.iter().all(|y| y.abs() <= 1.0));
+    }
+
+    #[test]
+    fn unit_test_phaser() {
+        use crate::effects::phaser::{Phaser, StereoPhaser, PhaserOrder, LfoSource};
+        use crate::core::lin_filter::AllPass1P;
+        use crate::traits::Process;
+        use std::f64::consts;
+        let peak = |p: &mut dyn Process<f64>, f: f64| {
+            let omega = consts::TAU * f / 44100.0;
+            let mut peak: f64 = 0.0;
+            for n in 0..44100 {
+                let y = p.step((omega * n as f64).sin());
+                if n > 22050 { peak = peak.max(y.abs()); }
+            }
+            peak
+        };
+
+        // the first order all-pass leaves the magnitude untouched
+        let mut ap = AllPass1P::new();
+        ap.cutoff = 1000.0;
+        assert!((peak(&mut ap, 300.0) - 1.0).abs() < 1e-3);
+        assert!((peak(&mut ap, 5000.0) - 1.0).abs() < 1e-3);
+
+        // two first order stages are 180 degrees out of phase at the cutoff, so
+        // the phaser makes a notch there
+        let mut phaser = Phaser::new(0);
+        phaser.set_stages(2);
+        phaser.depth = 0.0;
+        phaser.freq = 1000.0;
+        assert!(peak(&mut phaser, 1000.0) < 1e-3);
+        assert!(peak(&mut phaser, 100.0) > 0.9);
+
+        // two second order stages with q = 0.5 are 360 degrees out of phase at
+        // the cutoff, and 180 degrees at (sqrt(2) - 1) times the cutoff
+        phaser.order = PhaserOrder::Second;
+        phaser.set_stages(1);
+        assert!(phaser.stages() == 2);
+        assert!(peak(&mut phaser, 1000.0) > 0.999);
+        assert!(peak(&mut phaser, 1000.0 * (2.0_f64.sqrt() - 1.0)) < 1e-2);
+
+        // feedback stays stable even when pushed
+        phaser.feedback = 10.0;
+        phaser.set_stages(100);
+        assert!(phaser.stages() == 24);
+        assert!(peak(&mut phaser, 440.0).is_finite());
+
+        // random LFOs sweep both below and above the center frequency, the
+        // cutoff is recovered from the phase shift of a sine and a cosine,
+        // through two phasers with the same seed
+        for source in [LfoSource::SampleAndHold, LfoSource::SmoothRandom] {
+            let mut phaser_sin = Phaser::new(7);
+            let mut phaser_cos = Phaser::new(7);
+            for p in [&mut phaser_sin, &mut phaser_cos] {
+                p.set_stages(2);
+                p.mix = 1.0;
+                p.freq = 1000.0;
+                p.depth = 4.0;
+                p.set_rate(5.0);
+                p.lfo_source = source;
+            }
+            let omega = consts::TAU * 200.0 / 44100.0;
+            let (mut below, mut above) = (0, 0);
+            for n in 0..88200 {
+                let phase = omega * n as f64;
+                let y_sin = phaser_sin.step(phase.sin());
+                let y_cos = phaser_cos.step(phase.cos());
+                if n < 4410 { continue; }
+                // each first order stage shifts by 2 * atan(f / cutoff), warped
+                let shift = (phase - y_sin.atan2(y_cos)).rem_euclid(consts::TAU);
+                let cutoff = 44100.0 / consts::PI
+                    * ((0.5 * omega).tan() / (0.25 * shift).tan()).atan();
+                if cutoff < 900.0 { below += 1; }
+                if cutoff > 1100.0 { above += 1; }
+            }
+            assert!(below > 8820 && above > 8820);
+        }
+
+        // the two channels of the stereo phaser are swept differently
+        let mut stereo = StereoPhaser::new(0);
+        stereo.params().set_rate(5.0);
+        let mut diff: f64 = 0.0;
+        for n in 0..44100 {
+            let x = (consts::TAU * 440.0 * n as f64 / 44100.0).sin();
+            let (l, r) = stereo.step((x, x));
+            diff = diff.max((l - r).abs());
+        }
+        assert!(diff > 0.1);
+    }
+
+    #[test]
+    fn unit_test_formant_filter() {
+        use crate::effects::formant::{FormantFilter, Vowel, Voice};
+        use crate::traits::Process;
+        use std::f64::consts;
+        let peak = |p: &mut FormantFilter, f: f64| {
+            let omega = consts::TAU * f / 44100.0;
+            let mut peak: f64 = 0.0;
+            for n in 0..22050 {
+                let y = p.step((omega * n as f64).sin());
+                if n > 11025 { peak = peak.max(y.abs()); }
+            }
+            peak
+        };
+
+        // the first formant of the soprano "a" is at 800Hz, with 0dB gain
+        let mut filter = FormantFilter::new();
+        assert!((peak(&mut filter, 800.0) - 1.0).abs() < 0.2);
+        assert!(peak(&mut filter, 5000.0) < 0.05);
+
+        // "i" has its first formant much lower
+        filter.set_vowel(Vowel::I);
+        assert!(peak(&mut filter, 800.0) < 0.2);
+        assert!((peak(&mut filter, 270.0) - 1.0).abs() < 0.2);
+
+        // morphing through all vowels and voices doesn't jump
+        filter.voice = Voice::Bass;
+        let omega = consts::TAU * 220.0 / 44100.0;
+        let mut y_z1 = filter.step(0.0);
+        for n in 1..44100 {
+            filter.vowel = 4.0 * n as f64 / 44100.0;
+            if n == 22050 { filter.voice = Voice::Alto; }
+            let y = filter.step((omega * n as f64).sin());
+            assert!((y - y_z1).abs() < 0.1);
+            y_z1 = y;
+        }
+    }
+
+    #[test]
+    fn unit_test_analysis() {
+        use crate::utils::analysis::{response_from_ir, response_from_sweep, thd,
+            aliasing, dc_offset, noise_floor};
+        use crate::core::lin_filter::{BiquadLowPass, SvfLowPass};
+        use crate::core::EmptyProcess;
+        use crate::traits::Process;
+        use std::f64::consts;
+        struct Clip;
+        impl Process<f64> for Clip {
+            fn step(&mut self, x: f64) -> f64 { x.clamp(-0.5, 0.5) + 0.1 }
+        }
+
+        // butterworth low-pass is -3dB and -90 degrees at the cutoff, measured
+        // both from the impulse response and from a sweep
+        let mut lp = BiquadLowPass::new();
+        lp.cutoff = 1000.0;
+        let r = response_from_ir(&mut lp, 8192, 44100.0);
+        assert!((r.magnitude_db_at(1000.0) + 3.01).abs() < 0.01);
+        assert!((r.phase_at(1000.0) + consts::FRAC_PI_2).abs() < 1e-3);
+        assert!(r.magnitude_db_at(100.0).abs() < 0.01);
+        let mut lp = BiquadLowPass::new();
+        lp.cutoff = 1000.0;
+        let r = response_from_sweep(&mut lp, 20.0, 20000.0, 2.0, 8192, 44100.0);
+        assert!((r.magnitude_db_at(1000.0) + 3.01).abs() < 0.01);
+        assert!((r.phase_at(1000.0) + consts::FRAC_PI_2).abs() < 1e-3);
+
+        // the svf has a Q of 0.5 with no resonance, so it is -6dB at the cutoff
+        let mut svf = SvfLowPass::new();
+        svf.set_sr(44100.0);
+        svf.cutoff = 1000.0;
+        let r = response_from_ir(&mut svf, 8192, 44100.0);
+        assert!((r.magnitude_db_at(1000.0) + 6.02).abs() < 0.01);
+
+        // a clean process has no distortion, aliasing, DC or noise
+        let (t, t_n) = thd(&mut EmptyProcess {}, 1000.0, 1.0, 44100.0);
+        assert!(t < 1e-12 && t_n < 1e-12);
+        assert!(aliasing(&mut EmptyProcess {}, 5000.0, 1.0, 44100.0) < 1e-12);
+        assert!(noise_floor(&mut EmptyProcess {}, 44100.0) == 0.0);
+
+        // a hard clipper does, and the square wave it makes has odd harmonics
+        // all the way past nyquist, which alias
+        let (t, t_n) = thd(&mut Clip, 1000.0, 1.0, 44100.0);
+        assert!(t > 0.1 && t_n >= t);
+        assert!(aliasing(&mut Clip, 5000.0, 1.0, 44100.0) > 0.01);
+        assert!((dc_offset(&mut Clip, 1000.0, 0.0, 44100.0) - 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn unit_test_rolling_statistics() {
+        use crate::core::non_lin_filters::{RollingMedian, RollingMin, RollingMax,
+            RollingMean, RollingVariance, RollingRms};
+        use crate::core::chaos::NoiseWhite;
+        use crate::traits::{Process, Source};
+
+        // compare with brute force over the last `w` samples, including when the
+        // window changes length at run-time
+        let mut noise = NoiseWhite::new(3);
+        let x: Vec<f64> = (0..5000).map(|_| noise.step()).collect();
+        let mut median = RollingMedian::new();
+        let mut min = RollingMin::new();
+        let mut max = RollingMax::new();
+        let mut mean = RollingMean::new();
+        let mut var = RollingVariance::new();
+        let mut rms = RollingRms::new();
+        let mut w = 0;
+        let mut start = 0;
+        for n in 0..x.len() {
+            // growing the window keeps the samples already there
+            let new_w = match n { 0 => 7, 2000 => 100, 3500 => 4, _ => w };
+            if new_w != w {
+                if new_w > w { start = n.saturating_sub(w); }
+                w = new_w;
+                median.set_window(w);
+                min.set_window(w);
+                max.set_window(w);
+                mean.set_window(w);
+                var.set_window(w);
+                rms.set_window(w);
+            }
+            let lo = (n + 1).saturating_sub(w).max(start);
+            let win = &x[lo..=n];
+            let mut sorted = win.to_vec();
+            sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let k = sorted.len();
+            let med = if k % 2 == 1 { sorted[k / 2] }
+                else { 0.5 * (sorted[k / 2 - 1] + sorted[k / 2]) };
+            let mu = win.iter().sum::<f64>() / k as f64;
+            let ms = win.iter().map(|v| v * v).sum::<f64>() / k as f64;
+
+            assert!(median.step(x[n]) == med);
+            assert!(min.step(x[n]) == sorted[0]);
+            assert!(max.step(x[n]) == sorted[k - 1]);
+            assert!((mean.step(x[n]) - mu).abs() < 1e-12);
+            assert!((var.step(x[n]) - (ms - mu * mu)).abs() < 1e-12);
+            assert!((rms.step(x[n]) - ms.sqrt()).abs() < 1e-12);
+        }
+
+        // the median removes isolated spikes from a smooth signal
+        let mut median = RollingMedian::new();
+        median.set_window(3);
+        for n in 0..100 {
+            let smooth = n as f64 * 0.01;
+            let y = median.step(if n % 10 == 5 { 10.0 } else { smooth });
+            if n > 2 { assert!(y < 1.0); }
+        }
+    }
+
+    #[test]
+    fn unit_test_slew() {
+        use crate::core::non_lin_filters::{SlewLimiter, SlewClip1, SlewClip2};
+        use crate::traits::Process;
+        use std::f64::consts;
+
+        // a unit step takes 1ms to go through a 1000 units/s slew limiter, at
+        // any sample rate
+        for sr in [44100.0, 192000.0] {
+            let mut slew = SlewLimiter::new();
+            slew.set_sr(sr);
+            slew.fall = 10.0;
+            let ramp = (0.001 * sr) as usize;
+            for n in 0..2 * ramp {
+                let y = slew.step(1.0);
+                let expected = ((n + 1) as f64 / (0.001 * sr)).min(1.0);
+                assert!((y - expected).abs() < 1e-9);
+            }
+            // falling is slower
+            assert!((slew.step(0.0) - (1.0 - 10.0 / sr)).abs() < 1e-12);
+        }
+
+        // the slew clippers sound the same at 44.1kHz and 4x that rate
+        let run = |p: &mut dyn Process<f64>, sr: f64, len: usize| -> Vec<f64> {
+            let omega = consts::TAU * 1000.0 / sr;
+            (0..len).map(|n| p.step((omega * n as f64).sin())).collect()
+        };
+        let mut clip_1_a = SlewClip1::new();
+        let mut clip_1_b = SlewClip1::new();
+        let mut clip_2_a = SlewClip2::new();
+        let mut clip_2_b = SlewClip2::new();
+        clip_1_a.drive = 0.8;
+        clip_1_b.drive = 0.8;
+        clip_2_a.drive = 0.8;
+        clip_2_b.drive = 0.8;
+        clip_1_b.set_sr(176400.0);
+        clip_2_b.set_sr(176400.0);
+        let y_1_a = run(&mut clip_1_a, 44100.0, 4410);
+        let y_1_b = run(&mut clip_1_b, 176400.0, 4 * 4410);
+        let y_2_a = run(&mut clip_2_a, 44100.0, 4410);
+        let y_2_b = run(&mut clip_2_b, 176400.0, 4 * 4410);
+        // compare levels, as the differentiators add a slightly different
+        // delay at each sample rate
+        let rms = |y: &[f64]| (y.iter().map(|v| v * v).sum::<f64>() / y.len() as f64).sqrt();
+        let (rms_1_a, rms_1_b) = (rms(&y_1_a[2205..]), rms(&y_1_b[4 * 2205..]));
+        let (rms_2_a, rms_2_b) = (rms(&y_2_a[2205..]), rms(&y_2_b[4 * 2205..]));
+        assert!(rms_1_a > 0.1 && rms_2_a > 0.1);
+        assert!((rms_1_a / rms_1_b - 1.0).abs() < 0.01);
+        assert!((rms_2_a / rms_2_b - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn unit_test_adaa() {
+        use crate::core::waveshapers::{Adaa1, Adaa2, AdaaCurve, VarClip,
+            FastSigmoid, UnboundedSat};
+        use crate::utils::analysis::aliasing;
+        use crate::utils::math::{var_clip, fast_sigmoid, unbounded_sat};
+        use crate::traits::Process;
+        struct Gain<P: Process<f64>>(P);
+        impl<P: Process<f64>> Process<f64> for Gain<P> {
+            fn step(&mut self, x: f64) -> f64 { self.0.step(4.0 * x) }
+        }
+        struct Naive;
+        impl Process<f64> for Naive {
+            fn step(&mut self, x: f64) -> f64 { var_clip(x, 0.8) }
+        }
+
+        // the antiderivatives match the curves
+        let mut clip = VarClip::new();
+        clip.hardness = 0.8;
+        let curves: [&dyn AdaaCurve; 3] = [&clip, &FastSigmoid::new(), &UnboundedSat::new()];
+        let funcs: [&dyn Fn(f64) -> f64; 3] = [&|x| var_clip(x, 0.8), &fast_sigmoid, &unbounded_sat];
+        for (c, f) in curves.iter().zip(funcs.iter()) {
+            for x in [-7.0, -1.0, -0.3, 0.2, 0.9, 1.5, 12.0] {
+                let h = 1e-4;
+                assert!((c.f(x) - f(x)).abs() < 1e-12);
+                assert!(((c.f1(x + h) - c.f1(x - h)) / (2.0 * h) - f(x)).abs() < 1e-6);
+                assert!(((c.f2(x + h) - c.f2(x - h)) / (2.0 * h) - c.f1(x)).abs() < 1e-6);
+            }
+        }
+
+        // aliasing goes down with the order of ADAA
+        let mut clip_1 = VarClip::new();
+        let mut clip_2 = VarClip::new();
+        clip_1.hardness = 0.8;
+        clip_2.hardness = 0.8;
+        let naive = aliasing(&mut Gain(Naive), 5000.0, 1.0, 44100.0);
+        let adaa_1 = aliasing(&mut Gain(Adaa1::new(clip_1)), 5000.0, 1.0, 44100.0);
+        let adaa_2 = aliasing(&mut Gain(Adaa2::new(clip_2)), 5000.0, 1.0, 44100.0);
+        assert!(adaa_1 < 0.5 * naive && adaa_2 < 0.5 * adaa_1);
+
+        // slow signals, including the ill-conditioned constant and turning
+        // points, follow the curve, delayed by the latency
+        let mut sat_1 = Adaa1::new(UnboundedSat::new());
+        let mut sat_2 = Adaa2::new(VarClip::new());
+        for n in 0..20000 {
+            let x = |n: f64| 5.0 * (n * 0.0005).sin().max(0.5);
+            let y_1 = sat_1.step(x(n as f64));
+            let y_2 = sat_2.step(x(n as f64));
+            // skip the jump from the initial state
+            if n < 2 { continue; }
+            assert!((y_1 - unbounded_sat(x(n as f64 - 0.5))).abs() < 2e-3);
+            assert!((y_2 - var_clip(x(n as f64 - 1.0), 0.5)).abs() < 3e-3);
+        }
+    }
+
+    #[test]
+    fn unit_test_oversampling() {
+        use crate::core::oversampling::{Oversampler, Upsampler, Downsampler,
+            OversamplingFactor, OversamplingPhase};
+        use crate::core::EmptyProcess;
+        use crate::utils::analysis::{response_from_ir, aliasing};
+        use crate::traits::Process;
+        use std::f64::consts;
+        struct Clip;
+        impl Process<f64> for Clip {
+            fn step(&mut self, x: f64) -> f64 { (4.0 * x).clamp(-1.0, 1.0) }
+        }
+
+        for phase in [OversamplingPhase::Linear, OversamplingPhase::Minimum] {
+            for factor in [OversamplingFactor::X1, OversamplingFactor::X2,
+                OversamplingFactor::X8, OversamplingFactor::X16]
+            {
+                // flat passband, and the reported latency matches the group delay
+                let mut os = Oversampler::new(EmptyProcess {}, factor, phase);
+                let latency = os.latency();
+                let r = response_from_ir(&mut os, 8192, 44100.0);
+                for k in 1..=20 {
+                    assert!(r.magnitude_db_at(k as f64 * 1000.0).abs() < 0.01);
+                }
+                let delay = -r.phase_at(100.0) / (consts::TAU * 100.0 / 44100.0);
+                assert!((delay - latency).abs() < 1e-3);
+
+                // up and down sampling on their own give the same result
+                let mut up = Upsampler::new(factor, phase);
+                let mut down = Downsampler::new(factor, phase);
+                let mut os = Oversampler::new(EmptyProcess {}, factor, phase);
+                for n in 0..1000 {
+                    let x = (n as f64 * 0.3).sin();
+                    let block = up.step(x).to_vec();
+                    assert!(block.len() == factor as usize);
+                    assert!((down.step(&block) - os.step(x)).abs() < 1e-12);
+                }
+            }
+
+            // images above the host nyquist are rejected
+            let mut down = Downsampler::new(OversamplingFactor::X4, phase);
+            let mut peak: f64 = 0.0;
+            for n in 0..4000 {
+                let block: Vec<f64> = (0..4)
+                    .map(|i| ((4 * n + i) as f64 * consts::TAU * 30000.0 / 176400.0).sin())
+                    .collect();
+                let y = down.step(&block);
+                if n > 2000 { peak = peak.max(y.abs()); }
+            }
+            assert!(peak < 1e-3);
+
+            // oversampling reduces aliasing of a non-linear process
+            let naive = aliasing(&mut Clip, 5000.0, 1.0, 44100.0);
+            let mut os = Oversampler::new(Clip, OversamplingFactor::X8, phase);
+            assert!(aliasing(&mut os, 5000.0, 1.0, 44100.0) < 0.1 * naive);
+        }
+    }
+
+    #[test]
+    fn unit_test_resampling() {
+        use crate::core::resampling::{Resampler, FixedRate};
+        use crate::core::EmptyProcess;
+        use crate::traits::Process;
+        use std::f64::consts;
+
+        // a sine converted by different ratios comes out at the same frequency
+        for ratio in [0.0625, 0.3, 1.0, 44100.0 / 48000.0, 2.0, 16.0] {
+            let mut rs = Resampler::new();
+            rs.set_ratio(ratio);
+            let omega = 0.2 * consts::PI * ratio.min(1.0);
+            let mut out = vec![];
+            for n in 0..20000 {
+                rs.push((omega * n as f64).sin());
+                while let Some(y) = rs.pop() { out.push(y); }
+            }
+            for (k, y) in out.iter().enumerate().skip(out.len() / 2) {
+                assert!((y - (omega * k as f64 / ratio).sin()).abs() < 1e-4);
+            }
+        }
+
+        // time varying ratio is smooth, for a slow sine any click would show up
+        // as a large jump between consecutive samples
+        let mut rs = Resampler::new();
+        let mut prev = 0.0;
+        for n in 0..50000 {
+            rs.set_ratio(1.0 + 0.5 * (n as f64 * 0.001).sin());
+            rs.push((n as f64 * 0.01).sin());
+            while let Some(y) = rs.pop() {
+                assert!((y - prev).abs() < 0.02);
+                prev = y;
+            }
+        }
+
+        // a fixed rate process is delayed by the reported latency
+        for (host, internal) in [(96000.0, 44100.0), (44100.0, 48000.0), (176400.0, 11025.0)] {
+            let mut fixed = FixedRate::new(EmptyProcess {}, internal);
+            fixed.set_sr(host);
+            let latency = fixed.latency() as f64;
+            let omega = 0.2 * consts::PI * f64::min(internal / host, 1.0);
+            for n in 0..20000 {
+                let y = fixed.step((omega * n as f64).sin());
+                if n > 5000 {
+                    assert!((y - (omega * (n as f64 - latency)).sin()).abs() < 1e-4);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unit_test_clipping_saturation() {
+        use crate::utils::clipping::*;
+        use crate::utils::saturation::*;
+        use crate::core::waveshapers::{Waveshaper, ShaperCurve};
+        use crate::utils::analysis::dc_offset;
+        use crate::traits::Process;
+
+        let bounded: [fn(f64, f64, f64) -> f64; 6] = [hard_clip, cubic_clip,
+            tanh_clip, atan_clip, algebraic_clip, exponential];
+        let others: [fn(f64, f64, f64) -> f64; 4] = [tube, diode_pair, foldback, sine_fold];
+        for curve in bounded.iter().chain(others.iter()) {
+            // unit slope around zero, and silence stays silent with any bias
+            assert!((curve(1e-6, 1.0, 0.0) / 1e-6 - 1.0).abs() < 1e-3);
+            assert!((curve(1e-6, 3.0, 0.0) / 1e-6 - 3.0).abs() < 1e-2);
+            assert!(curve(0.0, 2.0, 0.3).abs() < 1e-12);
+        }
+        for curve in bounded.iter() {
+            for x in [-100.0, -3.0, -1.0, 0.5, 2.0, 1e6] {
+                assert!(curve(x, 1.0, 0.0).abs() <= 1.0);
+                assert!(curve(x, 1.0, 0.0).signum() == x.signum());
+            }
+        }
+
+        // curve specifics
+        assert!(hard_clip(0.7, 2.0, 0.0) == 1.0);
+        assert!((cubic_clip(1.5, 1.0, 0.0) - 1.0).abs() < 1e-12);
+        assert!((foldback(1.5, 1.0, 0.0) - 0.5).abs() < 1e-12);
+        assert!((foldback(3.5, 1.0, 0.0) + 0.5).abs() < 1e-12);
+        assert!(tube(10.0, 1.0, 0.0) < -tube(-10.0, 1.0, 0.0));
+        for x in [-20.0, -0.3, 0.1, 1.0, 5.0, 1e3] {
+            // the diode pair solves its circuit equation
+            let y = diode_pair(x, 1.0, 0.0);
+            assert!((y + 0.01 * (5.0 * y).sinh() - x * 1.05).abs() < 1e-9 * x.abs().max(1.0));
+        }
+
+        // DC is removed from asymmetric curves only
+        let mut shaper = Waveshaper::new();
+        shaper.curve = ShaperCurve::Tube;
+        shaper.drive = 4.0;
+        assert!(dc_offset(&mut shaper, 100.0, 1.0, 44100.0).abs() < 1e-3);
+        let mut shaper = Waveshaper::new();
+        shaper.curve = ShaperCurve::Tube;
+        shaper.drive = 4.0;
+        let raw = (0..44100).map(|n| tube((n as f64 * 0.01).sin(), 4.0, 0.0)).sum::<f64>();
+        assert!(raw / 44100.0 < -0.1);
+        shaper.curve = ShaperCurve::HardClip;
+        assert!(shaper.step(0.5) == 1.0);
+    }
+
+    #[test]
+    fn unit_test_wavefolder() {
+        use crate::effects::wavefolder::{Wavefolder, FoldCurve};
+        use crate::core::waveshapers::AdaaCurve;
+        use crate::utils::analysis::{aliasing, dc_offset};
+        use crate::traits::Process;
+        struct Naive(FoldCurve);
+        impl Process<f64> for Naive {
+            fn step(&mut self, x: f64) -> f64 { self.0.f(5.0 * x) }
+        }
+
+        // the curve folds at the odd thresholds and flattens after the last stage
+        let mut curve = FoldCurve::new();
+        curve.stages = 3;
+        assert!((curve.f(0.5) - 0.5).abs() < 0.01);
+        assert!((curve.f(2.0) - 0.0).abs() < 0.01);
+        assert!((curve.f(4.0) - 0.0).abs() < 0.01);
+        assert!((curve.f(-2.0) - 0.0).abs() < 0.01);
+        assert!((curve.f(3.0) + 1.0).abs() < 0.15);
+        assert!((curve.f(20.0) - curve.f(6.0)).abs() < 0.01);
+        for x in [-9.0, -2.5, 0.3, 1.0, 4.7, 12.0] {
+            assert!(curve.f(x).abs() <= 1.0);
+            // the antiderivatives match the curve
+            let h = 1e-5;
+            assert!(((curve.f1(x + h) - curve.f1(x - h)) / (2.0 * h) - curve.f(x)).abs() < 1e-7);
+            assert!(((curve.f2(x + h) - curve.f2(x - h)) / (2.0 * h) - curve.f1(x)).abs() < 1e-7);
+        }
+
+        // much less aliasing than folding sample by sample
+        let mut folder = Wavefolder::new();
+        folder.fold = 5.0;
+        let naive = aliasing(&mut Naive(FoldCurve::new()), 1000.0, 1.0, 44100.0);
+        assert!(aliasing(&mut folder, 1000.0, 1.0, 44100.0) < 0.05 * naive);
+
+        // asymmetric folding doesn't leave DC
+        let mut folder = Wavefolder::new();
+        folder.fold = 3.0;
+        folder.symmetry = 0.5;
+        assert!(dc_offset(&mut folder, 200.0, 1.0, 44100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn unit_test_bitcrusher() {
+        use crate::effects::bitcrusher::{Bitcrusher, Dither, NoiseShaping};
+        use crate::core::lin_filter::BiquadLowPass;
+        use crate::traits::Process;
+        use std::f64::consts;
+
+        // outputs land on the levels of the bit depth
+        let mut crusher = Bitcrusher::new(0);
+        crusher.bits = 4.0;
+        crusher.rate = 44100.0;
+        for n in 0..1000 {
+            let y = crusher.step((n as f64 * 0.01).sin() * 0.9);
+            assert!((y * 8.0 - (y * 8.0).round()).abs() < 1e-12);
+        }
+
+        // fractional rates hold each sample for the right average time
+        let mut crusher = Bitcrusher::new(0);
+        crusher.bits = 32.0;
+        crusher.rate = 44100.0 / 3.5;
+        let mut changes = 0;
+        let mut prev = -1.0;
+        for n in 0..35000 {
+            let y = crusher.step(n as f64 / 35000.0);
+            if y != prev { changes += 1; }
+            prev = y;
+        }
+        assert!(changes == 10000);
+
+        // with dither the error doesn't depend on the signal, so a slow ramp
+        // well below one step still comes through on average
+        for dither in [Dither::Rectangular, Dither::Triangular] {
+            let mut crusher = Bitcrusher::new(3);
+            crusher.bits = 3.0;
+            crusher.rate = 44100.0;
+            crusher.dither = dither;
+            let mean = (0..100000).map(|_| crusher.step(0.05)).sum::<f64>() / 100000.0;
+            assert!((mean - 0.05).abs() < 0.005);
+        }
+        let mut crusher = Bitcrusher::new(3);
+        crusher.bits = 3.0;
+        crusher.rate = 44100.0;
+        assert!(crusher.step(0.05) == 0.0);
+
+        // noise shaping moves the noise out of the low end, measured by
+        // low-passing the error
+        let mut low_noise = vec![];
+        for shaping in [NoiseShaping::Off, NoiseShaping::FirstOrder, NoiseShaping::SecondOrder] {
+            let mut crusher = Bitcrusher::new(5);
+            crusher.bits = 6.0;
+            crusher.rate = 44100.0;
+            crusher.dither = Dither::Triangular;
+            crusher.noise_shaping = shaping;
+            let err: Vec<f64> = (0..50000).map(|n| {
+                let x = 0.5 * (n as f64 * 0.003).sin();
+                crusher.step(x) - x
+            }).collect();
+            let mut lp_1 = BiquadLowPass::new();
+            let mut lp_2 = BiquadLowPass::new();
+            lp_1.cutoff = 500.0;
+            lp_2.cutoff = 500.0;
+            low_noise.push(err.iter().map(|e| lp_2.step(lp_1.step(*e)).powi(2)).sum::<f64>());
+        }
+        assert!(low_noise[1] < 0.01 * low_noise[0] && low_noise[2] < 0.01 * low_noise[1]);
+
+        // content above the reduced nyquist aliases down, unless pre-filtered
+        let mut raw = Bitcrusher::new(0);
+        raw.bits = 32.0;
+        let mut filtered = Bitcrusher::new(0);
+        filtered.bits = 32.0;
+        filtered.pre_filter = true;
+        let x = |n: usize| (n as f64 * consts::TAU * 12000.0 / 44100.0).sin();
+        let rms_raw = (0..44100).map(|n| raw.step(x(n)).powi(2)).sum::<f64>().sqrt();
+        let rms_filtered = (0..44100).map(|n| filtered.step(x(n)).powi(2)).sum::<f64>().sqrt();
+        assert!(rms_filtered < 0.05 * rms_raw);
+
+        // the post-filter smooths the steps, but keeps the signal
+        let mut smooth = Bitcrusher::new(0);
+        smooth.bits = 32.0;
+        smooth.post_filter = true;
+        let mut max_jump: f64 = 0.0;
+        let mut prev = 0.0;
+        for n in 0..44100 {
+            let y = smooth.step((n as f64 * consts::TAU * 100.0 / 44100.0).sin());
+            if n > 1000 {
+                assert!((y - (n as f64 * consts::TAU * 100.0 / 44100.0).sin()).abs() < 0.1);
+            }
+            max_jump = max_jump.max((y - prev).abs());
+            prev = y;
+        }
+        assert!(max_jump < 0.02);
+    }
+
+    #[test]
+    fn unit_test_bl_oscillators() {
+        use crate::core::osc::{BlSawOsc, BlPulseOsc, BlTriOsc, BlMorphOsc};
+        use crate::traits::Source;
+
+        // 125 cycles of 1250Hz, the aliases fall between the harmonics
+        let len = 4410;
+        let freq = 1250.0;
+        let naive_saw: Vec<f64> = (0..len)
+            .map(|n| 2.0 * (n as f64 * freq / 44100.0).fract() - 1.0)
+            .collect();
+        let naive_tri: Vec<f64> = naive_saw.iter().map(|s| 1.0 - 2.0 * s.abs()).collect();
+
+        let mut saw = BlSawOsc::new();
+        saw.set_freq(freq);
+        let saw: Vec<f64> = (0..len).map(|_| saw.step()).collect();
+        let mut square = BlPulseOsc::new();
+        square.set_freq(freq);
+        let square: Vec<f64> = (0..len).map(|_| square.step()).collect();
+        let mut tri = BlTriOsc::new();
+        tri.set_freq(freq);
+        let tri: Vec<f64> = (0..len).map(|_| tri.step()).collect();
+        let mut morph = BlMorphOsc::new();
+        morph.set_freq(freq);
+        let morph: Vec<f64> = (0..len).map(|_| morph.step()).collect();
+
+        let naive_saw_alias = alias_ratio(&naive_saw, 125);
+        let naive_tri_alias = alias_ratio(&naive_tri, 125);
+        assert!(alias_ratio(&saw, 125) < naive_saw_alias / 20.0);
+        assert!(alias_ratio(&square, 125) < naive_saw_alias / 20.0);
+        assert!(alias_ratio(&tri, 125) < naive_tri_alias / 10.0);
+        assert!(alias_ratio(&morph, 125) < naive_tri_alias / 5.0);
+
+        // the morphing oscillator matches the triangle at the center
+        for (a, b) in tri.iter().zip(morph.iter()) {
+            assert!((a - b).abs() < 1e-9);
+        }
+
+        // PWM, morphing and through-zero FM stay finite and in range
+        let mut pulse = BlPulseOsc::new();
+        let mut morph = BlMorphOsc::new();
+        let mut saw = BlSawOsc::new();
+        for n in 0..100000 {
+            let lfo = (n as f64 * 0.001).sin();
+            pulse.width = 0.5 + 0.6 * lfo;
+            morph.shape = 1.2 * lfo;
+            saw.set_freq(3000.0 * lfo + 500.0);
+            pulse.set_freq(2000.0 + 1500.0 * lfo);
+            morph.set_freq(8000.0 * lfo);
+            for y in [pulse.step(), morph.step(), saw.step()] {
+                assert!(y.is_finite() && y.abs() <= 1.2);
+            }
+        }
+    }
+
+    #[test]
+    fn unit_test_wavetable() {
+        use crate::core::wavetable::{Wavetable, WavetableOsc};
+        use crate::shared_enums::InterpMethod;
+        use crate::traits::Source;
+        use std::f64::consts;
+        use std::sync::Arc;
+
+        // a sine of odd length comes out as a sine
+        let cycle: Vec<f64> = (0..601).map(|n| (consts::TAU * n as f64 / 601.0).sin()).collect();
+        let mut osc = WavetableOsc::new(Arc::new(Wavetable::from_cycle(&cycle)));
+        osc.set_freq(100.0);
+        for interp in [InterpMethod::Linear, InterpMethod::Quadratic] {
+            osc.interp_mode = interp;
+            osc.set_phase(0.0);
+            for n in 0..1000 {
+                let expected = (consts::TAU * 100.0 * n as f64 / 44100.0).sin();
+                assert!((osc.step() - expected).abs() < 1e-4);
+            }
+        }
+
+        // a naive saw is band-limited at every frequency
+        let saw: Vec<f64> = (0..2048).map(|n| n as f64 / 1024.0 - 1.0).collect();
+        let naive: Vec<f64> = (0..4410).map(|n| 2.0 * (n as f64 * 1250.0 / 44100.0).fract() - 1.0).collect();
+        let mut osc = WavetableOsc::new(Arc::new(Wavetable::from_cycle(&saw)));
+        osc.set_freq(1250.0);
+        let out: Vec<f64> = (0..4410).map(|_| osc.step()).collect();
+        assert!(alias_ratio(&out, 125) < alias_ratio(&naive, 125) * 1e-6);
+        osc.set_freq(5000.0);
+        let out: Vec<f64> = (0..4410).map(|_| osc.step()).collect();
+        assert!(alias_ratio(&out, 500) < 1e-10);
+
+        // frames are crossfaded, halfway between opposite frames is silent,
+        // and the frames are reached at the ends of the position
+        let mut frames = cycle.clone();
+        frames.extend(cycle.iter().map(|x| -x));
+        let mut osc = WavetableOsc::new(Arc::new(Wavetable::from_frames(&frames, 601)));
+        osc.set_freq(100.0);
+        osc.position = 0.5;
+        assert!((0..1000).all(|_| osc.step().abs() < 1e-12));
+        osc.position = 1.0;
+        osc.set_phase(0.0);
+        for n in 0..1000 {
+            let expected = -(consts::TAU * 100.0 * n as f64 / 44100.0).sin();
+            assert!((osc.step() - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn unit_test_fm() {
+        use crate::core::envelopes::Adsr;
+        use crate::core::fm::{FmVoice, FmAlgorithm, OpFreq, OpShape};
+        use crate::traits::Source;
+        use std::f64::consts;
+
+        // envelope segments take the time they are set to
+        let mut env = Adsr::new();
+        env.attack = 10.0;
+        env.decay = 100.0;
+        env.sustain = 0.25;
+        env.release = 50.0;
+        env.note_on();
+        let attack = (0..10000).position(|_| env.step() >= 1.0).unwrap();
+        assert!((attack as f64 - 441.0).abs() <= 1.0);
+        let decay = (0..10000).position(|_| env.step() <= 0.25).unwrap();
+        assert!((decay as f64 - 4410.0).abs() <= 1.0);
+        assert!((0..1000).all(|_| env.step() == 0.25));
+        env.note_off();
+        let release = (0..10000).position(|_| env.step() <= 0.0).unwrap();
+        assert!(release < 2205 && !env.is_active());
+
+        // a two operator stack is exactly sin(wt + index * sin(wt))
+        let mut voice = FmVoice::<2>::new();
+        for op in voice.ops.iter_mut() {
+            op.env.attack = 0.0;
+            op.env.sustain = 1.0;
+        }
+        voice.ops[1].level = 2.0;
+        voice.note_on(440.0);
+        for n in 0..1000 {
+            let wt = consts::TAU * 440.0 * n as f64 / 44100.0;
+            assert!((voice.step() - (wt + 2.0 * wt.sin()).sin()).abs() < 1e-9);
+        }
+
+        // fixed frequencies ignore the note, parallel operators are mixed
+        let mut voice = FmVoice::<2>::new();
+        voice.set_algorithm(FmAlgorithm::Parallel);
+        for op in voice.ops.iter_mut() {
+            op.env.attack = 0.0;
+            op.env.sustain = 1.0;
+            op.freq_mode = OpFreq::Fixed(1000.0);
+        }
+        voice.note_on(123.0);
+        for n in 0..1000 {
+            let wt = consts::TAU * 1000.0 * n as f64 / 44100.0;
+            assert!((voice.step() - wt.sin()).abs() < 1e-9);
+        }
+
+        // heavy feedback and parabolic sines stay bounded, and the voice ends
+        // after the release
+        let mut voice = FmVoice::<6>::new();
+        for (i, op) in voice.ops.iter_mut().enumerate() {
+            op.freq_mode = OpFreq::Ratio(i as f64 + 0.5);
+            op.shape = OpShape::Parabolic;
+            op.feedback = 1.5;
+            op.level = 3.0;
+            op.env.release = 10.0;
+        }
+        voice.mix = [1.0 / 6.0; 6];
+        voice.note_on(220.0);
+        assert!((0..10000).all(|_| voice.step().abs() <= 3.0));
+        voice.note_off();
+        for _ in 0..2000 { voice.step(); }
+        assert!(!voice.is_active());
+    }
+
+    #[test]
+    fn unit_test_osc_sync() {
+        use crate::core::osc::{RampCore, SyncMode, BlSawOsc, BlTriOsc, AsymTriOsc};
+        use crate::core::oversampling::OversamplingFactor;
+        use crate::traits::Source;
+        use std::f64::consts;
+
+        // wraps are reported once per cycle, with the time left to the next sample
+        let mut ramp = RampCore::new();
+        ramp.set_freq(1000.0);
+        let inc = consts::TAU * 1000.0 / 44100.0;
+        let mut wraps = 0;
+        let mut pending: Option<f64> = None;
+        for _ in 0..44150 {
+            let phase = ramp.step();
+            if let Some(frac) = pending.take() {
+                assert!((phase - frac * inc).abs() < 1e-9);
+            }
+            pending = ramp.wrapped();
+            if pending.is_some() { wraps += 1; }
+        }
+        assert!(wraps == 1001);
+
+        // hard sync of 125 cycles of 1250Hz, the aliases fall between the
+        // harmonics of the master
+        let (len, freq, ratio) = (4410, 1250.0, 2.37);
+        let naive: Vec<f64> = (0..len).map(|n| {
+            let master = (n as f64 * freq / 44100.0).fract();
+            2.0 * (master * ratio).fract() - 1.0
+        }).collect();
+        let mut master = RampCore::new();
+        master.set_freq(freq);
+        let mut saw = BlSawOsc::new();
+        saw.set_freq(freq * ratio);
+        let mut tri = BlTriOsc::new();
+        tri.set_freq(-freq * ratio);
+        let (mut saw_out, mut tri_out) = (vec![], vec![]);
+        for _ in 0..len {
+            master.step();
+            if let Some(frac) = master.wrapped() {
+                saw.sync(SyncMode::Hard, frac);
+                tri.sync(SyncMode::Hard, frac);
+            }
+            saw_out.push(saw.step());
+            tri_out.push(tri.step());
+        }
+        let naive_alias = alias_ratio(&naive, 125);
+        assert!(alias_ratio(&saw_out, 125) < naive_alias / 20.0);
+        assert!(alias_ratio(&tri_out, 125) < naive_alias / 20.0);
+
+        // reversing sync keeps a triangle continuous
+        let mut master = RampCore::new();
+        master.set_freq(freq);
+        let mut tri = BlTriOsc::new();
+        tri.set_freq(freq * ratio);
+        let mut prev = tri.step();
+        for _ in 0..len {
+            master.step();
+            if let Some(frac) = master.wrapped() { tri.sync(SyncMode::Reverse, frac); }
+            let y = tri.step();
+            assert!((y - prev).abs() <= 4.0 * freq * ratio / 44100.0 + 1e-9);
+            prev = y;
+        }
+
+        // oversampled oscillators are synced too, the output repeats with the
+        // master's period of 100 samples, and they can be masters themselves
+        let mut master = AsymTriOsc::new();
+        master.set_oversampling(OversamplingFactor::X4);
+        master.set_freq(441.0);
+        let mut slave = AsymTriOsc::new();
+        slave.set_oversampling(OversamplingFactor::X4);
+        slave.set_freq(1234.0);
+        let out: Vec<f64> = (0..2000).map(|_| {
+            master.step();
+            if let Some(frac) = master.wrapped() { slave.sync(SyncMode::Hard, frac); }
+            slave.step()
+        }).collect();
+        for n in 1000..1900 {
+            assert!((out[n] - out[n + 100]).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn unit_test_additive() {
+        use crate::core::additive::AdditiveOsc;
+        use crate::traits::Source;
+        use num::complex::Complex;
+        use rustfft::FftPlanner;
+        use std::f64::consts;
+
+        // the recursive phasors don't drift from a true sine
+        let mut osc = AdditiveOsc::new();
+        osc.set_freq(1000.0);
+        for n in 0..441000 {
+            let expected = (consts::TAU * 1000.0 * n as f64 / 44100.0).sin();
+            assert!((osc.step() - expected).abs() < 1e-6);
+        }
+
+        // partials above nyquist are muted, 125 cycles of a 1250Hz saw with
+        // 100 harmonics has no energy outside of the harmonics below nyquist
+        let mut osc = AdditiveOsc::new();
+        osc.set_freq(1250.0);
+        let amps: Vec<f64> = (1..=100).map(|k| 1.0 / k as f64).collect();
+        osc.set_harmonics(&amps);
+        let out: Vec<f64> = (0..4410).map(|_| osc.step()).collect();
+        let mut spectrum: Vec<Complex<f64>> = out.iter().map(|x| Complex::new(*x, 0.0)).collect();
+        FftPlanner::new().plan_fft_forward(4410).process(&mut spectrum);
+        for (k, bin) in spectrum.iter().enumerate().take(2206) {
+            if k % 125 != 0 { assert!(bin.norm() < 1e-6); }
+        }
+
+        // resynthesis of an FFT frame reproduces the frame
+        let frame: Vec<f64> = (0..64).map(|n| {
+            (1..=28).map(|k| {
+                let w = consts::TAU * k as f64 * n as f64 / 64.0;
+                (1.0 / k as f64) * (w + k as f64).sin()
+            }).sum()
+        }).collect();
+        let mut spectrum: Vec<Complex<f64>> = frame.iter().map(|x| Complex::new(*x, 0.0)).collect();
+        FftPlanner::new().plan_fft_forward(64).process(&mut spectrum);
+        let mut osc = AdditiveOsc::new();
+        osc.set_from_fft(&spectrum);
+        for _ in 0..3 {
+            for x in frame.iter() {
+                assert!((osc.step() - x).abs() < 1e-9);
+            }
+        }
+        osc.step();
+        osc.reset();
+        assert!((osc.step() - frame[0]).abs() < 1e-9);
+    }
+
 
 }
\ No newline at end of file
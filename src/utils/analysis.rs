@@ -0,0 +1,246 @@
+//! Offline measurement of processes.
+//!
+//! These functions drive any `Process<f64>` with test signals and measure what
+//! comes out: impulse response, magnitude and phase response, harmonic
+//! distortion, aliasing, DC offset and noise floor. They are meant for testing
+//! and validating processes against theory, not for real-time use, as they
+//! allocate and run for many samples.
+//!
+//! # Caveats
+//! Processes are not reset between measurements, so measure on a freshly
+//! created process if the state matters. Distortion measurements let the
+//! process settle for one second before measuring, processes with longer
+//! tails (like reverbs) will give inaccurate results.
+
+use rustfft::FftPlanner;
+use num::complex::Complex;
+use std::f64::consts;
+
+use crate::traits::Process;
+
+// length of the spectra used for distortion measurements
+const SPECTRUM_LEN: usize = 65536;
+
+// highest harmonic counted as harmonic distortion
+const MAX_HARMONIC: usize = 10;
+
+/// Magnitude and phase response of a process, sampled at evenly spaced
+/// frequencies from 0 Hz to nyquist.
+pub struct Response {
+    /// frequency of each point in hertz
+    pub freq: Vec<f64>,
+    /// magnitude of each point in dB
+    pub magnitude_db: Vec<f64>,
+    /// phase of each point in radians, between -PI and PI
+    pub phase: Vec<f64>,
+}
+
+impl Response {
+    // builds the response from the first half of a spectrum
+    fn from_spectrum(spectrum: &[Complex<f64>], sr: f64) -> Self {
+        let n = spectrum.len();
+        let bins = n / 2 + 1;
+        Self {
+            freq: (0..bins).map(|k| k as f64 * sr / n as f64).collect(),
+            magnitude_db: spectrum[..bins].iter()
+                .map(|c| 20.0 * c.norm().max(1e-300).log10())
+                .collect(),
+            phase: spectrum[..bins].iter().map(|c| c.arg()).collect(),
+        }
+    }
+
+    // finds the point at or below `freq`, and the distance to the next point
+    fn locate(&self, freq: f64) -> (usize, f64) {
+        let step = self.freq[1] - self.freq[0];
+        let pos = (freq / step).clamp(0.0, (self.freq.len() - 1) as f64);
+        let i = (pos.floor() as usize).min(self.freq.len() - 2);
+        (i, pos - i as f64)
+    }
+
+    /// Magnitude in dB at `freq`, linearly interpolated between points.
+    pub fn magnitude_db_at(&self, freq: f64) -> f64 {
+        let (i, x) = self.locate(freq);
+        self.magnitude_db[i] * (1.0 - x) + self.magnitude_db[i + 1] * x
+    }
+
+    /// Phase in radians at `freq`, linearly interpolated between points, taking
+    /// the shortest way around the circle.
+    pub fn phase_at(&self, freq: f64) -> f64 {
+        let (i, x) = self.locate(freq);
+        let d = (self.phase[i + 1] - self.phase[i] + consts::PI)
+            .rem_euclid(consts::TAU) - consts::PI;
+        (self.phase[i] + d * x + consts::PI).rem_euclid(consts::TAU) - consts::PI
+    }
+}
+
+/// Captures `len` samples of the impulse response of a process.
+pub fn impulse_response(p: &mut dyn Process<f64>, len: usize) -> Vec<f64> {
+    (0..len).map(|n| p.step(if n == 0 { 1.0 } else { 0.0 })).collect()
+}
+
+/// Measures the frequency response of a process from its impulse response,
+/// captured for `len` samples. The resolution of the response is `sr / len`,
+/// with `len` rounded up to a power of 2.
+///
+/// # Caveats
+/// Only meaningful for linear processes, and `len` should be long enough for
+/// the impulse response to decay, otherwise the response is smeared.
+pub fn response_from_ir(p: &mut dyn Process<f64>, len: usize, sr: f64) -> Response {
+    let n = len.next_power_of_two().max(2);
+    let mut buf: Vec<Complex<f64>> = impulse_response(p, len).iter()
+        .map(|x| Complex::new(*x, 0.0))
+        .collect();
+    buf.resize(n, Complex::new(0.0, 0.0));
+    FftPlanner::new().plan_fft_forward(n).process(&mut buf);
+    Response::from_spectrum(&buf, sr)
+}
+
+/// Measures the frequency response of a process with an exponential sine sweep
+/// from `f_start` to `f_end` in hertz, lasting `duration` seconds, at -6dBFS.
+/// The impulse response is recovered by deconvolution and truncated to
+/// `ir_len` samples, which discards the harmonic distortion of the process,
+/// as its products land before the linear impulse response.
+///
+/// # Caveats
+/// The response is only accurate between `f_start` and `f_end`. Slightly
+/// nonlinear processes can be measured this way, but for strongly nonlinear
+/// processes the response depends on the sweep level.
+pub fn response_from_sweep(p: &mut dyn Process<f64>, f_start: f64, f_end: f64,
+    duration: f64, ir_len: usize, sr: f64) -> Response
+{
+    let sweep_len = (duration * sr) as usize;
+    let n = (sweep_len + ir_len).next_power_of_two();
+
+    // exponential sweep, followed by silence so that the tail is captured
+    let l = duration / (f_end / f_start).ln();
+    let x: Vec<f64> = (0..n).map(|i| {
+        if i >= sweep_len { return 0.0; }
+        let t = i as f64 / sr;
+        0.5 * (consts::TAU * f_start * l * ((t / l).exp() - 1.0)).sin()
+    }).collect();
+    let mut x_spec: Vec<Complex<f64>> = x.iter().map(|s| Complex::new(*s, 0.0)).collect();
+    let mut y_spec: Vec<Complex<f64>> = x.iter().map(|s| Complex::new(p.step(*s), 0.0)).collect();
+
+    let mut planner = FftPlanner::new();
+    let fft = planner.plan_fft_forward(n);
+    fft.process(&mut x_spec);
+    fft.process(&mut y_spec);
+
+    // regularized spectral division, avoids blowing up outside of the sweep band
+    let max_power = x_spec.iter().map(|c| c.norm_sqr()).fold(0.0, f64::max);
+    let eps = max_power * 1e-10;
+    let mut h: Vec<Complex<f64>> = x_spec.iter().zip(y_spec.iter())
+        .map(|(x, y)| y * x.conj() / (x.norm_sqr() + eps))
+        .collect();
+    planner.plan_fft_inverse(n).process(&mut h);
+
+    // keep only the linear part of the impulse response
+    let ir_n = ir_len.next_power_of_two().max(2);
+    let mut ir: Vec<Complex<f64>> = h.iter().take(ir_len)
+        .map(|c| Complex::new(c.re / n as f64, 0.0))
+        .collect();
+    ir.resize(ir_n, Complex::new(0.0, 0.0));
+    planner.plan_fft_forward(ir_n).process(&mut ir);
+    Response::from_spectrum(&ir, sr)
+}
+
+// drives the process with a sine, and returns the power spectrum of the output
+// and the bin of the fundamental. The frequency is rounded to a bin, so that the
+// fundamental, its harmonics and their aliases all land exactly on bins, and
+// no window is needed.
+fn sine_spectrum(p: &mut dyn Process<f64>, freq: f64, amplitude: f64, sr: f64)
+    -> (Vec<f64>, usize)
+{
+    let n = SPECTRUM_LEN;
+    let bin = ((freq * n as f64 / sr).round() as usize).clamp(1, n / 2 - 1);
+    let omega = consts::TAU * bin as f64 / n as f64;
+
+    // let the process settle for a second
+    let settle = sr as usize;
+    for i in 0..settle {
+        p.step(amplitude * (omega * i as f64).sin());
+    }
+    let mut buf: Vec<Complex<f64>> = (settle..settle + n)
+        .map(|i| Complex::new(p.step(amplitude * (omega * i as f64).sin()), 0.0))
+        .collect();
+    FftPlanner::new().plan_fft_forward(n).process(&mut buf);
+
+    // one sided power spectrum
+    let power = (0..=n / 2).map(|k| {
+        let scale = if k == 0 || k == n / 2 { 1.0 } else { 2.0 };
+        scale * buf[k].norm_sqr()
+    }).collect();
+    (power, bin)
+}
+
+/// Measures total harmonic distortion of a process, driven by a sine at `freq`
+/// hertz with peak `amplitude`.
+/// # Returns
+/// - `(thd, thd_n)`, as amplitude ratios to the fundamental. THD counts the
+///   harmonics up to the 10th, THD+N counts everything except the fundamental
+///   and DC, so it also includes noise and aliasing. Use
+///   `conversion::gain_to_db` to get the values in dB.
+///
+/// # Caveats
+/// `freq` is rounded to the closest multiple of `sr / 65536`.
+pub fn thd(p: &mut dyn Process<f64>, freq: f64, amplitude: f64, sr: f64) -> (f64, f64) {
+    let (power, bin) = sine_spectrum(p, freq, amplitude, sr);
+    let fundamental = power[bin];
+    let harmonics: f64 = (2..=MAX_HARMONIC)
+        .map(|h| h * bin)
+        .filter(|k| *k < power.len())
+        .map(|k| power[k])
+        .sum();
+    let total: f64 = power.iter().skip(1).sum();
+    (
+        (harmonics / fundamental).sqrt(),
+        ((total - fundamental).max(0.0) / fundamental).sqrt(),
+    )
+}
+
+/// Measures aliasing of a process, driven by a sine at `freq` hertz with peak
+/// `amplitude`. This is the content that is neither the fundamental, one of its
+/// harmonics below nyquist, nor DC, which for deterministic processes is made of
+/// the harmonics that folded back from above nyquist.
+/// # Returns
+/// - aliasing as an amplitude ratio to the fundamental
+///
+/// # Caveats
+/// `freq` is rounded to the closest multiple of `sr / 65536`, choose a
+/// frequency whose harmonics don't fold back exactly onto other harmonics.
+pub fn aliasing(p: &mut dyn Process<f64>, freq: f64, amplitude: f64, sr: f64) -> f64 {
+    let (power, bin) = sine_spectrum(p, freq, amplitude, sr);
+    let harmonic: f64 = (1..)
+        .map(|h| h * bin)
+        .take_while(|k| *k < power.len())
+        .map(|k| power[k])
+        .sum();
+    let total: f64 = power.iter().skip(1).sum();
+    ((total - harmonic).max(0.0) / power[bin]).sqrt()
+}
+
+/// Measures the DC offset at the output of a process, driven by a sine at `freq`
+/// hertz with peak `amplitude`, averaged over one second after settling. Use an
+/// amplitude of 0 to measure the DC offset on silence.
+pub fn dc_offset(p: &mut dyn Process<f64>, freq: f64, amplitude: f64, sr: f64) -> f64 {
+    let len = sr as usize;
+    let omega = consts::TAU * freq / sr;
+    for i in 0..len {
+        p.step(amplitude * (omega * i as f64).sin());
+    }
+    (len..2 * len)
+        .map(|i| p.step(amplitude * (omega * i as f64).sin()))
+        .sum::<f64>() / len as f64
+}
+
+/// Measures the noise floor of a process, as the RMS level of its output on
+/// silence, over one second after settling, with DC removed.
+pub fn noise_floor(p: &mut dyn Process<f64>, sr: f64) -> f64 {
+    let len = sr as usize;
+    for _ in 0..len {
+        p.step(0.0);
+    }
+    let out: Vec<f64> = (0..len).map(|_| p.step(0.0)).collect();
+    let mean = out.iter().sum::<f64>() / len as f64;
+    (out.iter().map(|y| (y - mean) * (y - mean)).sum::<f64>() / len as f64).sqrt()
+}
@@ -0,0 +1,53 @@
+//! Clipping curves, which limit the signal to a ceiling of 1.
+//!
+//! All curves have a slope of 1 around zero, so that quiet signals pass
+//! unchanged, and take the same `drive` and `bias` parameters:
+//! - `drive`: linear gain applied before the curve, more drive means more
+//!   clipping
+//! - `bias`: offset added after the drive, makes the clipping asymmetric, adding
+//!   even harmonics. The output is shifted back so that silence stays silent,
+//!   but the asymmetry still produces DC on loud signals.
+//!
+//! The curves are ordered from hardest to softest. For `Process` wrappers that
+//! also remove DC, see `core::waveshapers::Waveshaper`.
+
+use std::f64::consts;
+
+use crate::utils::math::fast_sigmoid;
+
+// applies drive and bias to a curve, so that zero maps to zero
+#[inline]
+pub(crate) fn drive_bias(curve: impl Fn(f64) -> f64, x: f64, drive: f64, bias: f64) -> f64 {
+    curve(drive * x + bias) - curve(bias)
+}
+
+/// Hard clipper, the signal is cut flat at the ceiling.
+pub fn hard_clip(x: f64, drive: f64, bias: f64) -> f64 {
+    drive_bias(|u| u.clamp(-1.0, 1.0), x, drive, bias)
+}
+
+/// Cubic soft clipper, `x - 4/27 x^3` up to 1.5, where it reaches the ceiling
+/// with a smooth knee. Only adds the 3rd harmonic until the knee.
+pub fn cubic_clip(x: f64, drive: f64, bias: f64) -> f64 {
+    drive_bias(|u| {
+        let u = u.clamp(-1.5, 1.5);
+        u - 4.0 / 27.0 * u * u * u
+    }, x, drive, bias)
+}
+
+/// Hyperbolic tangent clipper, the classic smooth saturation curve.
+pub fn tanh_clip(x: f64, drive: f64, bias: f64) -> f64 {
+    drive_bias(|u| u.tanh(), x, drive, bias)
+}
+
+/// Arctangent clipper, softer than `tanh_clip`, it approaches the ceiling
+/// slowly.
+pub fn atan_clip(x: f64, drive: f64, bias: f64) -> f64 {
+    drive_bias(|u| consts::FRAC_2_PI * (consts::FRAC_PI_2 * u).atan(), x, drive, bias)
+}
+
+/// Algebraic clipper, `x / sqrt(1 + x^2)`, cheap and even softer than
+/// `atan_clip`. Same curve as `math::fast_sigmoid`.
+pub fn algebraic_clip(x: f64, drive: f64, bias: f64) -> f64 {
+    drive_bias(fast_sigmoid, x, drive, bias)
+}
@@ -185,4 +185,26 @@ pub fn win_nuttal(n: f64, size: f64) -> f64 {
     0.355768 - 0.487396 * cosfull(tau_n_div_size) as f64 
              + 0.144232 * cosfull(2.0 * tau_n_div_size) as f64 
              + 0.012604 * cosfull(3.0 * tau_n_div_size) as f64
-}
\ No newline at end of file
+}
+
+/// Modified Bessel function of the first kind of order 0, as used by the kaiser
+/// window.
+pub fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let mut k = 1.0;
+    while term > sum * 1e-16 {
+        term *= (0.5 * x / k) * (0.5 * x / k);
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
+
+/// Kaiser window, `beta` trades the width of the main lobe for the level of
+/// the side lobes, 8.6 gives side lobes around -90dB.
+#[inline]
+pub fn win_kaiser(n: f64, size: f64, beta: f64) -> f64 {
+    let r = 2.0 * n / size - 1.0;
+    bessel_i0(beta * (1.0 - r * r).max(0.0).sqrt()) / bessel_i0(beta)
+}
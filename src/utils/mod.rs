@@ -1,10 +1,12 @@
-//! These modules contain pure functions, so they don't implement the Process
-//! trait.
+//! These modules contain helper functions, so they don't implement the Process
+//! trait. Most are pure functions on samples, while `analysis` drives existing
+//! processes to measure them.
 
-// pub mod clipping;            TODO:
-// pub mod saturation;          TODO:
+pub mod clipping;            // hard and soft clipping curves
+pub mod saturation;          // saturation, folding and diode curves
 pub mod math;                // crossfading
 pub mod conversion;          // pitch to freq, bpm to hz, pitch to 1v/oct
+pub mod analysis;            // offline measurement of processes
 
 #[cfg(feature = "no_fpu")]
 pub(crate) mod math_impl_no_fpu;
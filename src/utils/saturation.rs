@@ -0,0 +1,64 @@
+//! Saturation curves, with more character than the plain clipping curves.
+//!
+//! Takes the same `drive` and `bias` parameters as the curves in `clipping`:
+//! - `drive`: linear gain applied before the curve
+//! - `bias`: offset added after the drive, makes the curve asymmetric. The
+//!   output is shifted back so that silence stays silent.
+//!
+//! All curves have a slope of 1 around zero. Asymmetric curves, and any curve
+//! with a bias, produce DC on loud signals, the `Process` wrapper in
+//! `core::waveshapers::Waveshaper` removes it.
+
+use crate::utils::clipping::drive_bias;
+use crate::utils::math::var_clip;
+
+// diode pair constants, the diode current is DIODE_IS * sinh(DIODE_K * y)
+const DIODE_IS: f64 = 0.01;
+const DIODE_K: f64 = 5.0;
+
+/// Asymmetric tube-style saturation. The positive half compresses gently and
+/// never quite reaches the ceiling, like grid conduction, the negative half
+/// clips with a harder knee, like a tube going into cutoff. Rich in even
+/// harmonics.
+pub fn tube(x: f64, drive: f64, bias: f64) -> f64 {
+    drive_bias(|u| if u >= 0.0 { u / (1.0 + u) } else { var_clip(u, 0.8) }, x, drive, bias)
+}
+
+/// Static curve of a resistor feeding a pair of anti-parallel diodes, the
+/// output grows logarithmically above the knee instead of reaching a ceiling.
+/// Solves `x = y + Is sinh(k y)`, scaled to a unit slope, with a few
+/// iterations of Newton's method.
+pub fn diode_pair(x: f64, drive: f64, bias: f64) -> f64 {
+    drive_bias(|u| {
+        let v = u * (1.0 + DIODE_IS * DIODE_K);
+        let a = v.abs();
+        // both guesses overestimate the solution, and the curve is convex, so
+        // the iterations converge from above without overshooting
+        let mut y = a.min((a / DIODE_IS).asinh() / DIODE_K);
+        for _ in 0..6 {
+            let s = DIODE_IS * (DIODE_K * y).sinh();
+            let c = DIODE_IS * DIODE_K * (DIODE_K * y).cosh();
+            y -= (y + s - a) / (1.0 + c);
+        }
+        y.copysign(v)
+    }, x, drive, bias)
+}
+
+/// Foldback, the signal is reflected back from the ceiling instead of being
+/// clipped, and keeps folding between -1 and 1 as the drive increases.
+pub fn foldback(x: f64, drive: f64, bias: f64) -> f64 {
+    drive_bias(|u| 1.0 - ((u + 1.0).rem_euclid(4.0) - 2.0).abs(), x, drive, bias)
+}
+
+/// Sine folder, a smooth version of `foldback`, the signal is run through a
+/// sine, so it starts folding back at PI / 2.
+pub fn sine_fold(x: f64, drive: f64, bias: f64) -> f64 {
+    drive_bias(|u| u.sin(), x, drive, bias)
+}
+
+/// Exponential saturation, `1 - exp(-x)` mirrored for negative values. Unlike
+/// `tanh`, it starts bending right away, and it approaches the ceiling more
+/// slowly.
+pub fn exponential(x: f64, drive: f64, bias: f64) -> f64 {
+    drive_bias(|u| (1.0 - (-u.abs()).exp()).copysign(u), x, drive, bias)
+}